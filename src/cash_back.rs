@@ -1,18 +1,42 @@
+use std::collections::BTreeMap;
+
 use cosmwasm_std::{
-    Addr, CosmosMsg, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdResult, Uint128,
+    from_json, Addr, Coin, CosmosMsg, Decimal, Deps, DepsMut, Empty, Env, Event, MessageInfo,
+    Order, Response, StdError, StdResult, Storage, Uint128,
 };
+use cw20::Cw20ReceiveMsg;
+use cw_storage_plus::Bound;
 use oraiswap::asset::{Asset, AssetInfo};
 
 use crate::{
-    helpers::{build_transfer_msg, query_asset_balance},
+    helpers::{build_transfer_msg, query_asset_balance, query_nft_holder},
+    msg::{Cw20HookMsg, SimulateCashBackResponse, UserBonusesResponse, UserCashBackResponse},
     state::{
-        read_all_pending_cash_back, CAMPAIGN, CONFIG, LAST_CAMPAIGN_ID, PENDING_CASH_BACK,
-        USER_CASH_BACK, WHITELIST_CONTRACT,
+        read_all_pending_cash_back, read_pending_referral_rewards, read_user_campaigns, Campaign,
+        PausableFunction, RuleMode, UserStreak, CAMPAIGN, CAMPAIGN_BONUS, CAMPAIGN_MULTIPLIERS,
+        CONFIG, EXCLUDED_USER, INTEGRATION_QUOTA, INTEGRATION_USAGE, LAST_CAMPAIGN_ID, NFT_RULES,
+        PAUSE_STATUS, PENDING_CASH_BACK, PENDING_REFERRAL_REWARD, REFERRAL_EARNING, REFERRER,
+        USER_BOOST, USER_CAMPAIGNS, USER_CASH_BACK, USER_STREAK, WHITELIST_CONTRACT,
     },
     ContractError,
 };
 
+fn assert_not_paused(
+    storage: &dyn Storage,
+    function: PausableFunction,
+) -> Result<(), ContractError> {
+    if PAUSE_STATUS
+        .may_load(storage)?
+        .unwrap_or_default()
+        .is_paused(function)
+    {
+        return Err(ContractError::Paused { function });
+    }
+    Ok(())
+}
+
 pub fn execute_cash_back(deps: DepsMut) -> Result<Response, ContractError> {
+    assert_not_paused(deps.storage, PausableFunction::Payouts)?;
     let last_id = LAST_CAMPAIGN_ID.may_load(deps.storage)?.unwrap_or_default();
     if last_id == 0 {
         return Ok(Response::default());
@@ -20,10 +44,10 @@ pub fn execute_cash_back(deps: DepsMut) -> Result<Response, ContractError> {
     let campaign = CAMPAIGN.load(deps.storage, last_id)?;
     let pending = read_all_pending_cash_back(deps.storage);
 
-    let msgs: Vec<CosmosMsg> = pending
+    let msgs = pending
         .iter()
-        .map(|item| build_transfer_msg(&campaign.reward_token, item.1, &item.0).unwrap())
-        .collect();
+        .map(|item| build_transfer_msg(&campaign.reward_token, item.1, &item.0))
+        .collect::<StdResult<Vec<CosmosMsg>>>()?;
 
     // remove all pending cash back
     PENDING_CASH_BACK.clear(deps.storage);
@@ -37,86 +61,703 @@ pub fn execute_trigger_cash_back(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
-    user: Addr,
+    user: String,
+    tokens: Vec<Asset>,
+    strict: bool,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if !config.verify_fees {
+        // fees are only reported, attached funds would be kept by the contract
+        if !info.funds.is_empty() {
+            return Err(ContractError::FeesNotVerified {});
+        }
+        return trigger_cash_back(deps, env, &info.sender, user, tokens, strict);
+    }
+
+    // reported fees must be exactly the attached funds
+    verify_fee_funds(&tokens, &info.funds)?;
+    let treasury = config
+        .treasury
+        .as_ref()
+        .ok_or(ContractError::TreasuryRequired {})?;
+    let forward_msgs = build_forward_fee_msgs(treasury, &tokens)?;
+
+    Ok(
+        trigger_cash_back(deps, env, &info.sender, user, tokens, strict)?
+            .add_messages(forward_msgs),
+    )
+}
+
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    cw20_msg: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    match from_json(&cw20_msg.msg)? {
+        Cw20HookMsg::TriggerCashBack { user, strict } => {
+            let config = CONFIG.load(deps.storage)?;
+            if !config.verify_fees {
+                return Err(ContractError::FeesNotVerified {});
+            }
+            let treasury = config
+                .treasury
+                .as_ref()
+                .ok_or(ContractError::TreasuryRequired {})?;
+            // the hook sender is reported by the token contract, so only tokens listed
+            // for the calling contract are trusted with it
+            let caller = deps.api.addr_validate(&cw20_msg.sender)?;
+            let token = AssetInfo::Token {
+                contract_addr: info.sender,
+            };
+            let contract_info = WHITELIST_CONTRACT
+                .may_load(deps.storage, &caller)?
+                .ok_or(ContractError::NotWhitelisted {})?;
+            if !contract_info.allowed_fee_tokens.contains(&token) {
+                return Err(ContractError::Cw20FeeNotAllowed {});
+            }
+            // fee is the received amount of the sending cw20 token
+            let tokens = vec![Asset {
+                info: token,
+                amount: cw20_msg.amount,
+            }];
+            let forward_msgs = build_forward_fee_msgs(treasury, &tokens)?;
+
+            Ok(
+                trigger_cash_back(deps, env, &caller, user, tokens, strict.unwrap_or_default())?
+                    .add_messages(forward_msgs),
+            )
+        }
+    }
+}
+
+fn verify_fee_funds(tokens: &[Asset], funds: &[Coin]) -> Result<(), ContractError> {
+    let mut claimed: BTreeMap<&str, Uint128> = BTreeMap::new();
+    for token in tokens {
+        match &token.info {
+            AssetInfo::NativeToken { denom } => {
+                let amount = claimed.entry(denom).or_default();
+                *amount = amount.checked_add(token.amount)?;
+            }
+            // cw20 fees must be sent through Receive
+            AssetInfo::Token { .. } => return Err(ContractError::FeeMismatch {}),
+        }
+    }
+
+    let mut paid: BTreeMap<&str, Uint128> = BTreeMap::new();
+    for coin in funds {
+        let amount = paid.entry(&coin.denom).or_default();
+        *amount = amount.checked_add(coin.amount)?;
+    }
+    claimed.retain(|_, amount| !amount.is_zero());
+    paid.retain(|_, amount| !amount.is_zero());
+
+    if claimed != paid {
+        return Err(ContractError::FeeMismatch {});
+    }
+    Ok(())
+}
+
+fn build_forward_fee_msgs(treasury: &Addr, tokens: &[Asset]) -> StdResult<Vec<CosmosMsg>> {
+    tokens
+        .iter()
+        .filter(|token| !token.amount.is_zero())
+        .map(|token| build_transfer_msg(&token.info, token.amount, treasury))
+        .collect()
+}
+
+// reason why a trigger did not accrue any cash back
+pub enum SkipReason {
+    NotWhitelisted,
+    ContractDisabled,
+    Excluded { reason: String },
+    NoCampaign,
+    CampaignNotInProgress,
+    BudgetExhausted,
+    ZeroPercent,
+    ZeroAmount,
+    QuotaExceeded,
+    Paused,
+    Error { message: String },
+}
+
+impl SkipReason {
+    pub fn code(&self) -> &'static str {
+        match self {
+            SkipReason::NotWhitelisted => "not_whitelisted",
+            SkipReason::ContractDisabled => "contract_disabled",
+            SkipReason::Excluded { .. } => "excluded",
+            SkipReason::NoCampaign => "no_campaign",
+            SkipReason::CampaignNotInProgress => "campaign_not_in_progress",
+            SkipReason::BudgetExhausted => "budget_exhausted",
+            SkipReason::ZeroPercent => "zero_percent",
+            SkipReason::ZeroAmount => "zero_amount",
+            SkipReason::QuotaExceeded => "quota_exceeded",
+            SkipReason::Paused => "paused",
+            SkipReason::Error { .. } => "error",
+        }
+    }
+
+    // in strict mode, skipped triggers fail with these errors
+    pub fn into_error(self) -> ContractError {
+        match self {
+            SkipReason::NotWhitelisted => ContractError::NotWhitelisted {},
+            SkipReason::ContractDisabled => ContractError::ContractDisabled {},
+            SkipReason::Excluded { reason } => ContractError::UserExcluded { reason },
+            SkipReason::NoCampaign => ContractError::NoCampaign {},
+            SkipReason::CampaignNotInProgress => ContractError::CampaignNotInProgress {},
+            SkipReason::BudgetExhausted => ContractError::BudgetExhausted {},
+            SkipReason::ZeroPercent => ContractError::ZeroCashBackPercent {},
+            SkipReason::ZeroAmount => ContractError::ZeroCashBackAmount {},
+            SkipReason::QuotaExceeded => ContractError::QuotaExceeded {},
+            SkipReason::Paused => ContractError::Paused {
+                function: PausableFunction::Triggers,
+            },
+            SkipReason::Error { message } => ContractError::Std(StdError::generic_err(message)),
+        }
+    }
+}
+
+fn skip_cash_back(
+    user: impl AsRef<str>,
+    reason: SkipReason,
+    strict: bool,
+) -> Result<Response, ContractError> {
+    let mut event = Event::new("cash_back_skipped")
+        .add_attribute("user", user.as_ref())
+        .add_attribute("reason", reason.code());
+    match &reason {
+        SkipReason::Excluded { reason } => event = event.add_attribute("detail", reason),
+        SkipReason::Error { message } => event = event.add_attribute("detail", message),
+        _ => {}
+    }
+    if strict {
+        return Err(reason.into_error());
+    }
+    Ok(Response::new().add_event(event))
+}
+
+// Errors raised while accruing are reported as a skipped trigger unless strict mode is on,
+// so a pair calling with a plain WasmMsg never has its swap reverted by cash back
+fn trigger_cash_back(
+    mut deps: DepsMut,
+    env: Env,
+    caller: &Addr,
+    user: String,
+    tokens: Vec<Asset>,
+    strict: bool,
+) -> Result<Response, ContractError> {
+    match accrue_cash_back(deps.branch(), env, caller, &user, tokens, strict) {
+        Err(err) if !strict => skip_cash_back(
+            &user,
+            SkipReason::Error {
+                message: err.to_string(),
+            },
+            false,
+        ),
+        res => res,
+    }
+}
+
+// referral reward credited on a trigger, with the updated balances of the referrer
+struct Referral {
+    referrer: Addr,
+    amount: Uint128,
+    pending: Uint128,
+    earned: Uint128,
+}
+
+// all amounts are computed before any storage write, so a failure never leaves partial state
+fn accrue_cash_back(
+    deps: DepsMut,
+    env: Env,
+    caller: &Addr,
+    user: &str,
     tokens: Vec<Asset>,
+    strict: bool,
 ) -> Result<Response, ContractError> {
-    // check sender must be whitelisted
-    if !WHITELIST_CONTRACT
-        .query_hook(deps.as_ref(), info.sender.to_string())
+    if PAUSE_STATUS
+        .may_load(deps.storage)?
         .unwrap_or_default()
+        .triggers
     {
-        return Ok(Response::default());
+        return skip_cash_back(user, SkipReason::Paused, strict);
+    }
+
+    // check sender must be whitelisted and enabled
+    let contract_info = match WHITELIST_CONTRACT.may_load(deps.storage, caller)? {
+        Some(contract_info) if contract_info.enabled => contract_info,
+        Some(_) => return skip_cash_back(user, SkipReason::ContractDisabled, strict),
+        None => return skip_cash_back(user, SkipReason::NotWhitelisted, strict),
+    };
+    // an invalid user is reported only to whitelisted contracts
+    let user = &deps.api.addr_validate(user)?;
+
+    // excluded users never accrue cash back
+    if let Some(reason) = EXCLUDED_USER.may_load(deps.storage, user)? {
+        return skip_cash_back(user, SkipReason::Excluded { reason }, strict);
     }
 
     let last_id = LAST_CAMPAIGN_ID.may_load(deps.storage)?.unwrap_or_default();
     if last_id == 0 {
-        return Ok(Response::default());
+        return skip_cash_back(user, SkipReason::NoCampaign, strict);
     }
     let mut campaign = CAMPAIGN.load(deps.storage, last_id)?;
-    if !campaign.in_progress(&env) || campaign.distributed_amount == campaign.total_reward {
-        return Ok(Response::default());
+    if !campaign.in_progress(&env) {
+        return skip_cash_back(user, SkipReason::CampaignNotInProgress, strict);
+    }
+    if campaign.distributed_amount >= campaign.total_reward {
+        return skip_cash_back(user, SkipReason::BudgetExhausted, strict);
     }
 
-    let cash_back_percent = calc_cash_back_percent(deps.as_ref(), &user)?;
+    // first trade bonus and streak multiplier of this campaign
+    let bonus = CAMPAIGN_BONUS
+        .may_load(deps.storage, last_id)?
+        .unwrap_or_default();
+    let streak = USER_STREAK.may_load(deps.storage, (last_id, user))?;
+    let first_trade_bonus = match streak {
+        Some(_) => Uint128::zero(),
+        None => bonus.first_trade.unwrap_or_default(),
+    };
+    let streak = streak.unwrap_or_default().advance(UserStreak::today(&env));
+    let streak_multiplier = bonus.streak_multiplier(&streak);
+
+    let config = CONFIG.load(deps.storage)?;
+    let mut cash_back_percent = calc_cash_back_percent(deps.as_ref(), &env, user)?;
+    for multiplier in [
+        contract_info.multiplier,
+        campaign.window_multiplier(&env),
+        streak_multiplier,
+    ]
+    .into_iter()
+    .flatten()
+    {
+        cash_back_percent = cash_back_percent
+            .checked_mul(multiplier)
+            .map_err(StdError::from)?
+            .min(config.max_cash_back_percent);
+    }
 
     if cash_back_percent.is_zero() {
-        return Ok(Response::default());
+        return skip_cash_back(user, SkipReason::ZeroPercent, strict);
     }
 
-    let cash_back_tokens: Vec<Asset> = tokens
+    // fee tokens not accepted by this contract are ignored
+    let multipliers = CAMPAIGN_MULTIPLIERS
+        .may_load(deps.storage, last_id)?
+        .unwrap_or_default();
+    let contract_percent =
+        cash_back_percent.checked_mul(multipliers.contract_multiplier(caller))?;
+    let cash_back_tokens = tokens
         .iter()
-        .map(|token| Asset {
-            info: token.info.clone(),
-            amount: token.amount * cash_back_percent,
+        .filter(|token| contract_info.is_fee_token_allowed(&token.info))
+        .map(|token| {
+            let percent = contract_percent
+                .checked_mul(multipliers.token_multiplier(&token.info))?
+                .min(config.max_cash_back_percent);
+            Ok(Asset {
+                info: token.info.clone(),
+                amount: token.amount.checked_mul_floor(percent)?,
+            })
         })
-        .collect();
+        .collect::<Result<Vec<Asset>, ContractError>>()?;
 
     // convert fee tokens to cashBackToken
-    let cash_back_amount =
-        convert_to_reward_token(deps.as_ref(), &cash_back_tokens, &campaign.reward_token)?
-            .min(campaign.total_reward - campaign.distributed_amount);
-
-    PENDING_CASH_BACK.update(deps.storage, &user, |pending| -> StdResult<_> {
-        let mut pending = pending.unwrap_or_default();
-        pending += cash_back_amount;
-        Ok(pending)
-    })?;
-    USER_CASH_BACK.update(deps.storage, (last_id, &user), |total| -> StdResult<_> {
-        let mut total = total.unwrap_or_default();
-        total += cash_back_amount;
-        Ok(total)
-    })?;
-
-    campaign.distributed_amount += cash_back_amount;
+    let mut cash_back_amount =
+        convert_to_reward_token(deps.as_ref(), &cash_back_tokens, &campaign.reward_token)?;
+    // no fee earned anything, e.g. zero fees or fee tokens not accepted,
+    // so the first trade bonus is not paid and the streak is left untouched
+    if cash_back_amount.is_zero() {
+        return skip_cash_back(user, SkipReason::ZeroAmount, strict);
+    }
+    cash_back_amount = cash_back_amount.checked_add(first_trade_bonus)?.min(
+        campaign
+            .total_reward
+            .checked_sub(campaign.distributed_amount)?,
+    );
 
+    // limit by budget share and rate limit of the calling contract
+    let mut usage = INTEGRATION_USAGE
+        .may_load(deps.storage, (last_id, caller))?
+        .unwrap_or_default();
+    let quota = INTEGRATION_QUOTA.may_load(deps.storage, caller)?;
+    let mut quota_remaining = Uint128::MAX;
+    if let Some(quota) = &quota {
+        quota_remaining = quota.remaining(&usage, campaign.total_reward, &env);
+        if quota_remaining.is_zero() {
+            return skip_cash_back(user, SkipReason::QuotaExceeded, strict);
+        }
+        cash_back_amount = cash_back_amount.min(quota_remaining);
+    }
+
+    // credit the referrer a share of this cash back, within the remaining budget and quota
+    let mut referral = None;
+    if let Some(referrer) = REFERRER.may_load(deps.storage, user)? {
+        if !config.referral_percent.is_zero() && !EXCLUDED_USER.has(deps.storage, &referrer) {
+            let referral_amount = cash_back_amount
+                .checked_mul_floor(config.referral_percent)?
+                .min(
+                    campaign
+                        .total_reward
+                        .checked_sub(campaign.distributed_amount)?
+                        .checked_sub(cash_back_amount)?,
+                )
+                .min(quota_remaining.checked_sub(cash_back_amount)?);
+            if !referral_amount.is_zero() {
+                referral = Some(Referral {
+                    pending: PENDING_REFERRAL_REWARD
+                        .may_load(deps.storage, (&referrer, last_id))?
+                        .unwrap_or_default()
+                        .checked_add(referral_amount)?,
+                    earned: REFERRAL_EARNING
+                        .may_load(deps.storage, (&referrer, user))?
+                        .unwrap_or_default()
+                        .checked_add(referral_amount)?,
+                    referrer,
+                    amount: referral_amount,
+                });
+            }
+        }
+    }
+
+    // referral rewards come out of the same budget, so they count as usage of the integration
+    let accrued = match &referral {
+        Some(referral) => cash_back_amount.checked_add(referral.amount)?,
+        None => cash_back_amount,
+    };
+    if let Some(rate_limit) = quota.and_then(|quota| quota.rate_limit) {
+        usage.window_amount = usage
+            .window_amount(&rate_limit, &env)
+            .checked_add(accrued)?;
+        usage.window = rate_limit.period.window(&env);
+    }
+    usage.total = usage.total.checked_add(accrued)?;
+
+    let pending = PENDING_CASH_BACK
+        .may_load(deps.storage, user)?
+        .unwrap_or_default()
+        .checked_add(cash_back_amount)?;
+    let user_total = USER_CASH_BACK
+        .may_load(deps.storage, (last_id, user))?
+        .unwrap_or_default()
+        .checked_add(cash_back_amount)?;
+    campaign.distributed_amount = campaign.distributed_amount.checked_add(accrued)?;
+
+    INTEGRATION_USAGE.save(deps.storage, (last_id, caller), &usage)?;
+    PENDING_CASH_BACK.save(deps.storage, user, &pending)?;
+    USER_CASH_BACK.save(deps.storage, (last_id, user), &user_total)?;
+    USER_CAMPAIGNS.save(deps.storage, (user, last_id), &Empty {})?;
     CAMPAIGN.save(deps.storage, last_id, &campaign)?;
+    USER_STREAK.save(deps.storage, (last_id, user), &streak)?;
 
-    Ok(Response::new().add_attributes(vec![
+    let mut response = Response::new().add_attributes(vec![
         ("action", "trigger_cash_back"),
         ("user", user.as_str()),
+        ("cash_back_percent", &cash_back_percent.to_string()),
         ("cash_back_amount", &cash_back_amount.to_string()),
+    ]);
+    if !first_trade_bonus.is_zero() {
+        response = response.add_attribute("first_trade_bonus", first_trade_bonus.to_string());
+    }
+    if streak_multiplier.is_some() {
+        response = response.add_attribute("streak_days", streak.length.to_string());
+    }
+    if let Some(referral) = referral {
+        PENDING_REFERRAL_REWARD.save(
+            deps.storage,
+            (&referral.referrer, last_id),
+            &referral.pending,
+        )?;
+        REFERRAL_EARNING.save(deps.storage, (&referral.referrer, user), &referral.earned)?;
+        response = response.add_attributes(vec![
+            ("referrer", referral.referrer.as_str()),
+            ("referral_amount", &referral.amount.to_string()),
+        ]);
+    }
+    Ok(response)
+}
+
+pub fn execute_set_referrer(
+    deps: DepsMut,
+    info: MessageInfo,
+    referrer: String,
+) -> Result<Response, ContractError> {
+    let referrer = deps.api.addr_validate(&referrer)?;
+    if REFERRER.has(deps.storage, &info.sender) {
+        return Err(ContractError::ReferrerAlreadySet {});
+    }
+    if referrer == info.sender {
+        return Err(ContractError::SelfReferral {});
+    }
+    // walk up the referral chain of the referrer, it must not lead back to the sender
+    let mut current = referrer.clone();
+    while let Some(next) = REFERRER.may_load(deps.storage, &current)? {
+        if next == info.sender {
+            return Err(ContractError::ReferralCycle {});
+        }
+        current = next;
+    }
+
+    REFERRER.save(deps.storage, &info.sender, &referrer)?;
+    REFERRAL_EARNING.save(deps.storage, (&referrer, &info.sender), &Uint128::zero())?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "set_referrer"),
+        ("user", info.sender.as_str()),
+        ("referrer", referrer.as_str()),
     ]))
 }
 
-pub fn calc_cash_back_percent(deps: Deps, user: &Addr) -> StdResult<Decimal> {
+pub fn execute_claim_referral_reward(
+    deps: DepsMut,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    assert_not_paused(deps.storage, PausableFunction::Claims)?;
+    // rewards are paid in the reward token of the campaign they were earned in
+    let rewards = read_pending_referral_rewards(deps.storage, Some(&info.sender))?;
+    if rewards.is_empty() {
+        return Err(ContractError::NothingToClaim {});
+    }
+
+    let ids = PENDING_REFERRAL_REWARD
+        .prefix(&info.sender)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<u64>>>()?;
+    for id in ids {
+        PENDING_REFERRAL_REWARD.remove(deps.storage, (&info.sender, id));
+    }
+
+    let mut response = Response::new().add_attributes(vec![
+        ("action", "claim_referral_reward"),
+        ("referrer", info.sender.as_str()),
+    ]);
+    for reward in rewards {
+        response = response
+            .add_attribute("amount", reward.to_string())
+            .add_message(build_transfer_msg(
+                &reward.info,
+                reward.amount,
+                &info.sender,
+            )?);
+    }
+    Ok(response)
+}
+
+pub fn calc_cash_back_percent(deps: Deps, env: &Env, user: &Addr) -> StdResult<Decimal> {
     let config = CONFIG.load(deps.storage)?;
 
     let balance = query_asset_balance(&deps.querier, user, &config.underlying_token);
+    let mut percent = calc_rule_percent(&config.rules, config.rule_mode, balance);
+
+    // apply bonus of every nft collection the user holds
+    for rule in NFT_RULES.may_load(deps.storage)?.unwrap_or_default() {
+        if query_nft_holder(&deps.querier, user, &rule.collection, rule.min_tokens) {
+            percent = rule.apply(percent);
+        }
+    }
 
+    // apply boost of this user if still active
+    if let Some(boost) = USER_BOOST.may_load(deps.storage, user)? {
+        if !boost.is_expired(env) {
+            percent = boost.apply(percent);
+        }
+    }
+
+    Ok(percent.min(config.max_cash_back_percent))
+}
+
+// rules must be sorted by threshold desc
+pub fn calc_rule_percent(
+    rules: &[(Uint128, Decimal)],
+    mode: RuleMode,
+    balance: Uint128,
+) -> Decimal {
     // Because amount sort by desc, so find best level by iterating through rules and finding the first matching rule
-    let percent = config
-        .rules
+    let Some(index) = rules
         .iter()
-        .find_map(|(threshold, cash_back_percent)| {
-            if *threshold <= balance {
-                Some(*cash_back_percent)
-            } else {
-                None
+        .position(|(threshold, _)| *threshold <= balance)
+    else {
+        return Decimal::zero();
+    };
+    let (threshold, percent) = rules[index];
+
+    match mode {
+        RuleMode::Step => percent,
+        RuleMode::Linear => {
+            // highest tier reached, nothing to interpolate
+            if index == 0 {
+                return percent;
+            }
+            let (next_threshold, next_percent) = rules[index - 1];
+            let progress = Decimal::from_ratio(balance - threshold, next_threshold - threshold);
+            percent.saturating_add(next_percent.saturating_sub(percent) * progress)
+        }
+    }
+}
+
+pub fn query_simulate_cash_back(
+    deps: Deps,
+    env: Env,
+    user: String,
+    tokens: Vec<Asset>,
+) -> StdResult<SimulateCashBackResponse> {
+    let user = deps.api.addr_validate(&user)?;
+    if EXCLUDED_USER.has(deps.storage, &user) {
+        return Ok(SimulateCashBackResponse {
+            cash_back_percent: Decimal::zero(),
+            cash_back_amount: Uint128::zero(),
+        });
+    }
+    let mut cash_back_percent = calc_cash_back_percent(deps, &env, &user)?;
+    let mut cash_back_amount = Uint128::zero();
+
+    let last_id = LAST_CAMPAIGN_ID.may_load(deps.storage)?.unwrap_or_default();
+    if last_id > 0 && !cash_back_percent.is_zero() {
+        let campaign = CAMPAIGN.load(deps.storage, last_id)?;
+        if campaign.in_progress(&env) {
+            let config = CONFIG.load(deps.storage)?;
+            if let Some(multiplier) = campaign.window_multiplier(&env) {
+                cash_back_percent = cash_back_percent
+                    .saturating_mul(multiplier)
+                    .min(config.max_cash_back_percent);
             }
+            // calling contract is unknown, only fee token multipliers apply
+            let multipliers = CAMPAIGN_MULTIPLIERS
+                .may_load(deps.storage, last_id)?
+                .unwrap_or_default();
+            let cash_back_tokens: Vec<Asset> = tokens
+                .iter()
+                .map(|token| {
+                    let percent = cash_back_percent
+                        .saturating_mul(multipliers.token_multiplier(&token.info))
+                        .min(config.max_cash_back_percent);
+                    Ok(Asset {
+                        info: token.info.clone(),
+                        amount: token
+                            .amount
+                            .checked_mul_floor(percent)
+                            .map_err(|err| StdError::generic_err(err.to_string()))?,
+                    })
+                })
+                .collect::<StdResult<Vec<Asset>>>()?;
+            cash_back_amount =
+                convert_to_reward_token(deps, &cash_back_tokens, &campaign.reward_token)?.min(
+                    campaign
+                        .total_reward
+                        .saturating_sub(campaign.distributed_amount),
+                );
+        }
+    }
+
+    Ok(SimulateCashBackResponse {
+        cash_back_percent,
+        cash_back_amount,
+    })
+}
+
+pub fn query_user_bonuses(deps: Deps, env: Env, user: String) -> StdResult<UserBonusesResponse> {
+    let user = deps.api.addr_validate(&user)?;
+    let campaign_id = LAST_CAMPAIGN_ID.may_load(deps.storage)?.unwrap_or_default();
+    let bonus = CAMPAIGN_BONUS
+        .may_load(deps.storage, campaign_id)?
+        .unwrap_or_default();
+    let streak = USER_STREAK.may_load(deps.storage, (campaign_id, &user))?;
+    let first_trade_bonus = match streak {
+        Some(_) => Uint128::zero(),
+        None => bonus.first_trade.unwrap_or_default(),
+    };
+    let streak = streak.unwrap_or_default().advance(UserStreak::today(&env));
+
+    Ok(UserBonusesResponse {
+        campaign_id,
+        first_trade_bonus,
+        streak_days: streak.length,
+        streak_multiplier: bonus.streak_multiplier(&streak),
+    })
+}
+
+pub fn query_user_cash_back(
+    deps: Deps,
+    user: String,
+    campaign_id: u64,
+) -> StdResult<UserCashBackResponse> {
+    let user = deps.api.addr_validate(&user)?;
+    let campaign = CAMPAIGN.load(deps.storage, campaign_id)?;
+    let earned = USER_CASH_BACK
+        .may_load(deps.storage, (campaign_id, &user))?
+        .unwrap_or_default();
+    let later = earned_after(deps.storage, &user, campaign_id)?;
+    let mut unpaid = PENDING_CASH_BACK
+        .may_load(deps.storage, &user)?
+        .unwrap_or_default()
+        .saturating_sub(later);
+
+    Ok(user_cash_back(campaign, earned, &mut unpaid))
+}
+
+pub fn query_user_history(
+    deps: Deps,
+    user: String,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<UserCashBackResponse>> {
+    let user = deps.api.addr_validate(&user)?;
+    let campaigns = read_user_campaigns(deps.storage, &user, start_after, limit)?;
+    let later = match campaigns.last() {
+        Some((id, _)) => earned_after(deps.storage, &user, *id)?,
+        None => Uint128::zero(),
+    };
+    let mut unpaid = PENDING_CASH_BACK
+        .may_load(deps.storage, &user)?
+        .unwrap_or_default()
+        .saturating_sub(later);
+
+    // pending is allocated from the latest campaign backwards
+    let mut history = campaigns
+        .into_iter()
+        .rev()
+        .map(|(id, earned)| {
+            Ok(user_cash_back(
+                CAMPAIGN.load(deps.storage, id)?,
+                earned,
+                &mut unpaid,
+            ))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+    history.reverse();
+    Ok(history)
+}
+
+// total cash back earned by the user in campaigns after `campaign_id`
+fn earned_after(storage: &dyn Storage, user: &Addr, campaign_id: u64) -> StdResult<Uint128> {
+    USER_CAMPAIGNS
+        .prefix(user)
+        .keys(
+            storage,
+            Some(Bound::exclusive(campaign_id)),
+            None,
+            Order::Ascending,
+        )
+        .try_fold(Uint128::zero(), |total, id| {
+            Ok(total.checked_add(USER_CASH_BACK.load(storage, (id?, user))?)?)
         })
-        .unwrap_or(Decimal::zero());
+}
 
-    Ok(percent)
+// a payout sends all pending cash back, so what is still pending was earned most recently
+fn user_cash_back(
+    campaign: Campaign,
+    earned: Uint128,
+    unpaid: &mut Uint128,
+) -> UserCashBackResponse {
+    let pending = earned.min(*unpaid);
+    *unpaid -= pending;
+    UserCashBackResponse {
+        campaign_id: campaign.id,
+        reward_token: campaign.reward_token,
+        earned,
+        claimed: earned - pending,
+        pending,
+    }
 }
 
 pub fn convert_to_reward_token(
@@ -128,7 +769,14 @@ pub fn convert_to_reward_token(
     let reward_token_price = get_token_price(deps, reward_token)?;
     for token in tokens {
         let price = get_token_price(deps, &token.info)?;
-        total_cash_back += token.amount * (price.checked_div(reward_token_price).unwrap())
+        let ratio = price
+            .checked_div(reward_token_price)
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+        let amount = token
+            .amount
+            .checked_mul_floor(ratio)
+            .map_err(|err| StdError::generic_err(err.to_string()))?;
+        total_cash_back = total_cash_back.checked_add(amount)?;
     }
 
     Ok(total_cash_back)
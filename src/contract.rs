@@ -1,25 +1,50 @@
+use std::cmp::Reverse;
+
 #[cfg(not(feature = "library"))]
 use cosmwasm_std::entry_point;
 use cosmwasm_std::{
-    to_json_binary, Addr, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Response, StdError,
-    StdResult, Uint128,
+    to_json_binary, Addr, Binary, Decimal, Deps, DepsMut, Env, MessageInfo, Order, Response,
+    StdError, StdResult, Uint128, Uint64,
 };
+use cw2::{set_contract_version, CONTRACT};
 use oraiswap::asset::AssetInfo;
-// use cw2::set_contract_version;
+use semver::Version;
 
-use crate::cash_back::{execute_cash_back, execute_trigger_cash_back};
+use crate::cash_back::{
+    execute_cash_back, execute_claim_referral_reward, execute_receive, execute_set_referrer,
+    execute_trigger_cash_back, query_simulate_cash_back, query_user_bonuses, query_user_cash_back,
+    query_user_history,
+};
 use crate::error::ContractError;
-use crate::helpers::validate_cash_back_rule;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::helpers::{
+    query_asset_balance, validate_asset_info, validate_asset_infos, validate_campaign_multipliers,
+    validate_cash_back_rule, validate_fee_settings, validate_max_cash_back_percent,
+    validate_nft_rules, validate_optional_addr, validate_referral_percent, validate_timelock,
+    DEFAULT_MAX_CASH_BACK_PERCENT,
+};
+use crate::migration::{migrate_keys, migrate_state, LEGACY_VERSION};
+use crate::msg::{
+    AdminLogInfo, AssetSolvency, CampaignEditInfo, CampaignMultipliersInfo, ExcludedUserInfo,
+    ExecuteMsg, InstantiateMsg, IntegrationUsageResponse, MigrateMsg, NftRuleInfo,
+    OwnershipResponse, PendingConfigChangeInfo, QueryMsg, RefereeInfo, RoleInfo, UserBoostInfo,
+    WhitelistContractResponse,
+};
 use crate::state::{
-    Campaign, Config, CAMPAIGN, CONFIG, LAST_CAMPAIGN_ID, PENDING_CASH_BACK, WHITELIST_CONTRACT,
+    append_admin_log, read_admin_log, read_all_pending_cash_back, read_campaign_edits,
+    read_excluded_users, read_integration_usages, read_pending_config_changes,
+    read_pending_referral_rewards, read_referees, read_roles, read_user_boosts,
+    read_whitelist_contracts, AdminAction, BoostKind, Campaign, CampaignBonus, CampaignEdit,
+    CampaignMultipliers, Config, ConfigUpdate, ContractKind, IntegrationQuota, MultiplierWindow,
+    NftRule, PausableFunction, PendingConfigChange, PendingOwner, RateLimit, Role, RuleMode,
+    WhitelistContractInfo, CAMPAIGN, CAMPAIGN_BONUS, CAMPAIGN_EDITS, CAMPAIGN_MULTIPLIERS, CONFIG,
+    EXCLUDED_USER, INTEGRATION_QUOTA, KEY_MIGRATION, LAST_CAMPAIGN_ID, LAST_CONFIG_CHANGE_ID,
+    NFT_RULES, PAUSE_STATUS, PENDING_CASH_BACK, PENDING_CONFIG_CHANGE, PENDING_OWNER, REFERRER,
+    ROLES, USER_BOOST, WHITELIST_CONTRACT,
 };
 
-/*
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:cash-back-contracts";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
-*/
 
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn instantiate(
@@ -28,23 +53,77 @@ pub fn instantiate(
     _info: MessageInfo,
     msg: InstantiateMsg,
 ) -> Result<Response, ContractError> {
+    let max_cash_back_percent = msg
+        .max_cash_back_percent
+        .unwrap_or(Decimal::percent(DEFAULT_MAX_CASH_BACK_PERCENT));
+    validate_max_cash_back_percent(max_cash_back_percent)?;
     let mut rules = msg.rules.unwrap_or_default();
-    validate_cash_back_rule(&rules)?;
-    rules.sort_by(|a, b| b.0.cmp(&a.0));
+    validate_cash_back_rule(&rules, max_cash_back_percent)?;
+    rules.sort_by_key(|rule| Reverse(rule.0));
+    let referral_percent = msg.referral_percent.unwrap_or_default();
+    validate_referral_percent(referral_percent)?;
+    validate_asset_info(deps.api, &msg.underlying_token)?;
+    let treasury = msg
+        .treasury
+        .map(|treasury| deps.api.addr_validate(&treasury))
+        .transpose()?;
+    let verify_fees = msg.verify_fees.unwrap_or_default();
+    validate_fee_settings(verify_fees, treasury.as_ref())?;
+    let timelock = msg.timelock.unwrap_or_default();
+    validate_timelock(timelock)?;
 
     CONFIG.save(
         deps.storage,
         &Config {
-            owner: msg.owner,
+            owner: Some(deps.api.addr_validate(&msg.owner)?),
             underlying_token: msg.underlying_token,
             rules,
+            max_cash_back_percent,
+            rule_mode: msg.rule_mode.unwrap_or_default(),
+            verify_fees,
+            treasury,
+            referral_percent,
+            timelock,
         },
     )?;
     LAST_CAMPAIGN_ID.save(deps.storage, &0)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
     Ok(Response::default())
 }
 
+#[cfg_attr(not(feature = "library"), entry_point)]
+pub fn migrate(mut deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    // contracts deployed before versioning have no cw2 info
+    let stored_version = match CONTRACT.may_load(deps.storage)? {
+        Some(info) if info.contract != CONTRACT_NAME => {
+            return Err(ContractError::InvalidContractName {
+                contract: info.contract,
+            })
+        }
+        Some(info) => info.version,
+        None => LEGACY_VERSION.to_string(),
+    };
+    let stored = Version::parse(&stored_version)?;
+    let new = Version::parse(CONTRACT_VERSION)?;
+    if stored > new {
+        return Err(ContractError::CannotDowngrade {
+            stored: stored_version,
+            new: CONTRACT_VERSION.to_string(),
+        });
+    }
+
+    let migrated = migrate_state(deps.branch(), &stored)?;
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "migrate"),
+        ("from_version", &stored_version),
+        ("to_version", CONTRACT_VERSION),
+        ("migrations", &migrated.len().to_string()),
+    ]))
+}
+
 #[cfg_attr(not(feature = "library"), entry_point)]
 pub fn execute(
     deps: DepsMut,
@@ -54,91 +133,688 @@ pub fn execute(
 ) -> Result<Response, ContractError> {
     match msg {
         ExecuteMsg::UpdateConfig {
-            owner,
             underlying_token,
             rules,
-        } => execute_update_config(deps, info, owner, underlying_token, rules),
-        ExecuteMsg::WhitelistContract { contract } => {
-            execute_whitelist_contract(deps, info, contract)
+            max_cash_back_percent,
+            rule_mode,
+            verify_fees,
+            treasury,
+            referral_percent,
+            timelock,
+        } => execute_update_config(
+            deps,
+            env,
+            info,
+            underlying_token,
+            rules,
+            max_cash_back_percent,
+            rule_mode,
+            verify_fees,
+            treasury,
+            referral_percent,
+            timelock,
+        ),
+        ExecuteMsg::ExecuteConfigChange { id } => execute_config_change(deps, env, info, id),
+        ExecuteMsg::CancelConfigChange { id } => execute_cancel_config_change(deps, env, info, id),
+        ExecuteMsg::WhitelistContract {
+            contract,
+            label,
+            kind,
+            multiplier,
+            allowed_fee_tokens,
+        } => execute_whitelist_contract(
+            deps,
+            env,
+            info,
+            contract,
+            WhitelistContractInfo {
+                label: label.unwrap_or_default(),
+                kind: kind.unwrap_or_default(),
+                multiplier,
+                allowed_fee_tokens: allowed_fee_tokens.unwrap_or_default(),
+                enabled: true,
+            },
+        ),
+        ExecuteMsg::UpdateWhitelistContract {
+            contract,
+            label,
+            kind,
+            multiplier,
+            allowed_fee_tokens,
+            enabled,
+        } => execute_update_whitelist_contract(
+            deps,
+            env,
+            info,
+            contract,
+            label,
+            kind,
+            multiplier,
+            allowed_fee_tokens,
+            enabled,
+        ),
+        ExecuteMsg::SetIntegrationQuota {
+            contract,
+            budget_share,
+            rate_limit,
+        } => execute_set_integration_quota(deps, info, contract, budget_share, rate_limit),
+        ExecuteMsg::RemoveContract { contract } => {
+            execute_remove_contract(deps, env, info, contract)
         }
-        ExecuteMsg::RemoveContract { contract } => execute_remove_contract(deps, info, contract),
         ExecuteMsg::CreateCampaign {
             start,
             end,
             reward_token,
             total_reward,
-        } => execute_create_campaign(deps, env, info, start, end, reward_token, total_reward),
+            require_solvency,
+        } => execute_create_campaign(
+            deps,
+            env,
+            info,
+            start,
+            end,
+            reward_token,
+            total_reward,
+            require_solvency.unwrap_or_default(),
+        ),
         ExecuteMsg::EditCampaign {
             id,
             start,
             end,
             total_reward,
-        } => execute_edit_campaign(deps, env, info, id, start, end, total_reward),
-        ExecuteMsg::TriggerCashBack { user, tokens } => {
-            execute_trigger_cash_back(deps, env, info, user, tokens)
+            require_solvency,
+        } => execute_edit_campaign(
+            deps,
+            env,
+            info,
+            id,
+            start,
+            end,
+            total_reward,
+            require_solvency.unwrap_or_default(),
+        ),
+        ExecuteMsg::TriggerCashBack {
+            user,
+            tokens,
+            strict,
+        } => execute_trigger_cash_back(deps, env, info, user, tokens, strict.unwrap_or_default()),
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
+        ExecuteMsg::SetUserBoosts { boosts } => execute_set_user_boosts(deps, env, info, boosts),
+        ExecuteMsg::RemoveUserBoosts { users } => execute_remove_user_boosts(deps, info, users),
+        ExecuteMsg::ExcludeUsers { users } => execute_exclude_users(deps, info, users),
+        ExecuteMsg::IncludeUsers { users } => execute_include_users(deps, info, users),
+        ExecuteMsg::SetNftRules { rules } => execute_set_nft_rules(deps, info, rules),
+        ExecuteMsg::AddCampaignWindow { id, window } => {
+            execute_add_campaign_window(deps, env, info, id, window)
+        }
+        ExecuteMsg::RemoveCampaignWindow { id, start } => {
+            execute_remove_campaign_window(deps, info, id, start)
+        }
+        ExecuteMsg::SetCampaignMultipliers { id, multipliers } => {
+            execute_set_campaign_multipliers(deps, info, id, multipliers)
+        }
+        ExecuteMsg::SetCampaignBonus { id, bonus } => {
+            execute_set_campaign_bonus(deps, info, id, bonus)
+        }
+        ExecuteMsg::SetReferrer { referrer } => execute_set_referrer(deps, info, referrer),
+        ExecuteMsg::ClaimReferralReward {} => execute_claim_referral_reward(deps, info),
+        ExecuteMsg::ProposeOwner { owner, expires } => {
+            execute_propose_owner(deps, env, info, owner, expires)
+        }
+        ExecuteMsg::AcceptOwnership {} => execute_accept_ownership(deps, env, info),
+        ExecuteMsg::CancelOwnershipProposal {} => execute_cancel_ownership_proposal(deps, info),
+        ExecuteMsg::RenounceOwnership {} => execute_renounce_ownership(deps, info),
+        ExecuteMsg::Pause { function } => execute_set_paused(deps, info, function, true),
+        ExecuteMsg::Unpause { function } => execute_set_paused(deps, info, function, false),
+        ExecuteMsg::GrantRole { user, role } => execute_grant_role(deps, info, user, role),
+        ExecuteMsg::RevokeRole { user, role } => execute_revoke_role(deps, info, user, role),
+        ExecuteMsg::CashBack {} => {
+            assert_role(deps.as_ref(), &info.sender, Role::Keeper)?;
+            execute_cash_back(deps)
+        }
+        ExecuteMsg::MigrateKeys { limit } => {
+            assert_role(deps.as_ref(), &info.sender, Role::Admin)?;
+            let next = migrate_keys(deps, limit)?;
+            Ok(Response::new().add_attributes(vec![
+                ("action", "migrate_keys"),
+                ("done", &next.is_none().to_string()),
+            ]))
         }
-        ExecuteMsg::CashBack {} => execute_cash_back(deps),
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn execute_update_config(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    owner: Option<Addr>,
     underlying_token: Option<AssetInfo>,
     rules: Option<Vec<(Uint128, Decimal)>>,
+    max_cash_back_percent: Option<Decimal>,
+    rule_mode: Option<RuleMode>,
+    verify_fees: Option<bool>,
+    treasury: Option<String>,
+    referral_percent: Option<Decimal>,
+    timelock: Option<u64>,
 ) -> Result<Response, ContractError> {
-    let mut config = CONFIG.load(deps.storage)?;
-    if info.sender != config.owner {
-        return Err(ContractError::Unauthorized {});
+    let update = ConfigUpdate {
+        underlying_token,
+        rules: rules.map(|mut rules| {
+            rules.sort_by_key(|rule| Reverse(rule.0));
+            rules
+        }),
+        max_cash_back_percent,
+        rule_mode,
+        verify_fees,
+        treasury: treasury
+            .map(|treasury| deps.api.addr_validate(&treasury))
+            .transpose()?,
+        referral_percent,
+        timelock,
+    };
+    assert_config_update_roles(deps.as_ref(), &info.sender, &update)?;
+
+    if let Some(underlying_token) = &update.underlying_token {
+        validate_asset_info(deps.api, underlying_token)?;
+    }
+    if let Some(max_cash_back_percent) = update.max_cash_back_percent {
+        validate_max_cash_back_percent(max_cash_back_percent)?;
+    }
+    if let Some(referral_percent) = update.referral_percent {
+        validate_referral_percent(referral_percent)?;
+    }
+    if let Some(timelock) = update.timelock {
+        validate_timelock(timelock)?;
     }
 
-    if let Some(owner) = owner {
-        config.owner = owner;
+    let config = CONFIG.load(deps.storage)?;
+    if config.timelock == 0 {
+        apply_config_update(
+            deps,
+            &env,
+            &info.sender,
+            AdminAction::UpdateConfig {},
+            update,
+        )?;
+        return Ok(Response::default().add_attribute("action", "update_config"));
     }
 
-    if let Some(underlying_token) = underlying_token {
-        config.underlying_token = underlying_token;
+    // check the change against the current config now, it is checked again when executed
+    let mut new_config = config.clone();
+    update.clone().apply(&mut new_config);
+    validate_cash_back_rule(&new_config.rules, new_config.max_cash_back_percent)?;
+    validate_fee_settings(new_config.verify_fees, new_config.treasury.as_ref())?;
+
+    let id = LAST_CONFIG_CHANGE_ID
+        .may_load(deps.storage)?
+        .unwrap_or_default()
+        + 1;
+    let change = PendingConfigChange {
+        proposer: info.sender.clone(),
+        execute_after: Uint64::from(env.block.time.seconds())
+            .checked_add(Uint64::from(config.timelock))?
+            .u64(),
+        update,
+    };
+    PENDING_CONFIG_CHANGE.save(deps.storage, id, &change)?;
+    LAST_CONFIG_CHANGE_ID.save(deps.storage, &id)?;
+    append_admin_log(
+        deps.storage,
+        &env,
+        &info.sender,
+        AdminAction::QueueConfigChange { id },
+        None,
+        Some(&change),
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "queue_config_change"),
+        ("id", &id.to_string()),
+        ("execute_after", &change.execute_after.to_string()),
+    ]))
+}
+
+fn execute_config_change(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    let change = PENDING_CONFIG_CHANGE
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::ConfigChangeNotFound {})?;
+    if env.block.time.seconds() < change.execute_after {
+        return Err(ContractError::ConfigChangeLocked {
+            execute_after: change.execute_after,
+        });
     }
+    // the proposer may have lost the roles since queuing
+    assert_config_update_roles(deps.as_ref(), &change.proposer, &change.update)?;
+
+    PENDING_CONFIG_CHANGE.remove(deps.storage, id);
+    apply_config_update(
+        deps,
+        &env,
+        &info.sender,
+        AdminAction::ExecuteConfigChange {
+            id,
+            proposer: change.proposer,
+        },
+        change.update,
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "execute_config_change"),
+        ("id", &id.to_string()),
+    ]))
+}
+
+fn execute_cancel_config_change(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::Admin)?;
+
+    let change = PENDING_CONFIG_CHANGE
+        .may_load(deps.storage, id)?
+        .ok_or(ContractError::ConfigChangeNotFound {})?;
+    PENDING_CONFIG_CHANGE.remove(deps.storage, id);
+    append_admin_log(
+        deps.storage,
+        &env,
+        &info.sender,
+        AdminAction::CancelConfigChange { id },
+        Some(&change),
+        None,
+    )?;
 
-    if let Some(mut rules) = rules {
-        validate_cash_back_rule(&rules)?;
-        rules.sort_by(|a, b| b.0.cmp(&a.0));
-        config.rules = rules;
+    Ok(Response::new().add_attributes(vec![
+        ("action", "cancel_config_change"),
+        ("id", &id.to_string()),
+    ]))
+}
+
+// fee settings are managed by treasurers, the rest by admins
+fn assert_config_update_roles(
+    deps: Deps,
+    sender: &Addr,
+    update: &ConfigUpdate,
+) -> Result<(), ContractError> {
+    let fee_update = update.verify_fees.is_some() || update.treasury.is_some();
+    if fee_update {
+        assert_role(deps, sender, Role::Treasurer)?;
+    }
+    if !fee_update
+        || update.underlying_token.is_some()
+        || update.rules.is_some()
+        || update.max_cash_back_percent.is_some()
+        || update.rule_mode.is_some()
+        || update.referral_percent.is_some()
+        || update.timelock.is_some()
+    {
+        assert_role(deps, sender, Role::Admin)?;
     }
+    Ok(())
+}
+
+fn apply_config_update(
+    deps: DepsMut,
+    env: &Env,
+    sender: &Addr,
+    action: AdminAction,
+    update: ConfigUpdate,
+) -> Result<(), ContractError> {
+    let old_config = CONFIG.load(deps.storage)?;
+    let mut config = old_config.clone();
+    update.apply(&mut config);
+    // re-check current rules, max percent may have been lowered
+    validate_cash_back_rule(&config.rules, config.max_cash_back_percent)?;
+    validate_fee_settings(config.verify_fees, config.treasury.as_ref())?;
     CONFIG.save(deps.storage, &config)?;
+    append_admin_log(
+        deps.storage,
+        env,
+        sender,
+        action,
+        Some(&old_config),
+        Some(&config),
+    )?;
+    Ok(())
+}
+
+fn execute_propose_owner(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    owner: String,
+    expires: Option<u64>,
+) -> Result<Response, ContractError> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let config = CONFIG.load(deps.storage)?;
+    if !config.is_owner(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
 
-    Ok(Response::default().add_attribute("action", "update_config"))
+    let pending_owner = PendingOwner { owner, expires };
+    if pending_owner.is_expired(&env) {
+        return Err(ContractError::OwnershipProposalExpired {});
+    }
+    PENDING_OWNER.save(deps.storage, &pending_owner)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "propose_owner"),
+        ("pending_owner", pending_owner.owner.as_str()),
+    ]))
 }
-fn execute_whitelist_contract(
+
+fn execute_accept_ownership(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    let pending_owner = PENDING_OWNER
+        .may_load(deps.storage)?
+        .ok_or(ContractError::NoOwnershipProposal {})?;
+    if pending_owner.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+    if pending_owner.is_expired(&env) {
+        return Err(ContractError::OwnershipProposalExpired {});
+    }
+
+    let mut config = CONFIG.load(deps.storage)?;
+    config.owner = Some(pending_owner.owner);
+    CONFIG.save(deps.storage, &config)?;
+    PENDING_OWNER.remove(deps.storage);
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "accept_ownership"),
+        ("owner", info.sender.as_str()),
+    ]))
+}
+
+fn execute_cancel_ownership_proposal(
     deps: DepsMut,
     info: MessageInfo,
-    contract: Addr,
 ) -> Result<Response, ContractError> {
     let config = CONFIG.load(deps.storage)?;
-    if config.owner != info.sender {
+    if !config.is_owner(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    if !PENDING_OWNER.exists(deps.storage) {
+        return Err(ContractError::NoOwnershipProposal {});
+    }
+    PENDING_OWNER.remove(deps.storage);
+
+    Ok(Response::new().add_attribute("action", "cancel_ownership_proposal"))
+}
+
+// leave the contract without owner, every owner message fails afterwards
+fn execute_renounce_ownership(deps: DepsMut, info: MessageInfo) -> Result<Response, ContractError> {
+    let mut config = CONFIG.load(deps.storage)?;
+    if !config.is_owner(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
 
-    WHITELIST_CONTRACT.add_hook(deps.storage, contract.clone())?;
+    config.owner = None;
+    CONFIG.save(deps.storage, &config)?;
+    PENDING_OWNER.remove(deps.storage);
+
+    // admins act with the rights of the owner, so their grants go with the ownership
+    let members = ROLES
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(Addr, Vec<Role>)>>>()?;
+    for (user, mut roles) in members {
+        if !roles.contains(&Role::Admin) {
+            continue;
+        }
+        roles.retain(|item| *item != Role::Admin);
+        if roles.is_empty() {
+            ROLES.remove(deps.storage, &user);
+        } else {
+            ROLES.save(deps.storage, &user, &roles)?;
+        }
+    }
+
+    Ok(Response::new().add_attribute("action", "renounce_ownership"))
+}
+
+// the owner and admins are allowed to perform every role
+fn assert_role(deps: Deps, sender: &Addr, role: Role) -> Result<(), ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    if config.is_owner(sender) {
+        return Ok(());
+    }
+    let roles = ROLES.may_load(deps.storage, sender)?.unwrap_or_default();
+    if roles.contains(&Role::Admin) || roles.contains(&role) {
+        return Ok(());
+    }
+    Err(ContractError::Unauthorized {})
+}
+
+fn execute_grant_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    user: String,
+    role: Role,
+) -> Result<Response, ContractError> {
+    let user = deps.api.addr_validate(&user)?;
+    assert_role(deps.as_ref(), &info.sender, Role::Admin)?;
+
+    let mut roles = ROLES.may_load(deps.storage, &user)?.unwrap_or_default();
+    if !roles.contains(&role) {
+        roles.push(role);
+        ROLES.save(deps.storage, &user, &roles)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "grant_role"),
+        ("user", user.as_str()),
+        ("role", &format!("{:?}", role)),
+    ]))
+}
+
+fn execute_revoke_role(
+    deps: DepsMut,
+    info: MessageInfo,
+    user: String,
+    role: Role,
+) -> Result<Response, ContractError> {
+    let user = deps.api.addr_validate(&user)?;
+    assert_role(deps.as_ref(), &info.sender, Role::Admin)?;
+
+    let mut roles = ROLES.may_load(deps.storage, &user)?.unwrap_or_default();
+    roles.retain(|item| *item != role);
+    if roles.is_empty() {
+        ROLES.remove(deps.storage, &user);
+    } else {
+        ROLES.save(deps.storage, &user, &roles)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "revoke_role"),
+        ("user", user.as_str()),
+        ("role", &format!("{:?}", role)),
+    ]))
+}
+
+fn execute_set_paused(
+    deps: DepsMut,
+    info: MessageInfo,
+    function: Option<PausableFunction>,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::Guardian)?;
+
+    let mut status = PAUSE_STATUS.may_load(deps.storage)?.unwrap_or_default();
+    status.set(function, paused);
+    PAUSE_STATUS.save(deps.storage, &status)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", if paused { "pause" } else { "unpause" }),
+        (
+            "function",
+            &function
+                .map(|function| format!("{:?}", function))
+                .unwrap_or_else(|| "All".to_string()),
+        ),
+    ]))
+}
+
+fn execute_whitelist_contract(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: String,
+    contract_info: WhitelistContractInfo,
+) -> Result<Response, ContractError> {
+    let contract = deps.api.addr_validate(&contract)?;
+    validate_asset_infos(deps.api, &contract_info.allowed_fee_tokens)?;
+    assert_role(deps.as_ref(), &info.sender, Role::WhitelistManager)?;
+
+    if WHITELIST_CONTRACT.has(deps.storage, &contract) {
+        return Err(ContractError::AlreadyWhitelisted {});
+    }
+    WHITELIST_CONTRACT.save(deps.storage, &contract, &contract_info)?;
+    append_admin_log(
+        deps.storage,
+        &env,
+        &info.sender,
+        AdminAction::WhitelistContract {
+            contract: contract.clone(),
+        },
+        None,
+        Some(&contract_info),
+    )?;
 
     Ok(Response::new().add_attributes(vec![
         ("action", "whitelist_contract"),
         ("contract", contract.as_str()),
+        ("kind", &format!("{:?}", contract_info.kind)),
     ]))
 }
 
-fn execute_remove_contract(
+#[allow(clippy::too_many_arguments)]
+fn execute_update_whitelist_contract(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    contract: Addr,
+    contract: String,
+    label: Option<String>,
+    kind: Option<ContractKind>,
+    multiplier: Option<Decimal>,
+    allowed_fee_tokens: Option<Vec<AssetInfo>>,
+    enabled: Option<bool>,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    if config.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
+    let contract = deps.api.addr_validate(&contract)?;
+    assert_role(deps.as_ref(), &info.sender, Role::WhitelistManager)?;
+
+    let old_info = WHITELIST_CONTRACT
+        .may_load(deps.storage, &contract)?
+        .ok_or(ContractError::NotWhitelisted {})?;
+    let mut contract_info = old_info.clone();
+
+    if let Some(label) = label {
+        contract_info.label = label;
+    }
+    if let Some(kind) = kind {
+        contract_info.kind = kind;
+    }
+    if let Some(multiplier) = multiplier {
+        contract_info.multiplier = Some(multiplier);
     }
+    if let Some(allowed_fee_tokens) = allowed_fee_tokens {
+        validate_asset_infos(deps.api, &allowed_fee_tokens)?;
+        contract_info.allowed_fee_tokens = allowed_fee_tokens;
+    }
+    if let Some(enabled) = enabled {
+        contract_info.enabled = enabled;
+    }
+    WHITELIST_CONTRACT.save(deps.storage, &contract, &contract_info)?;
+    append_admin_log(
+        deps.storage,
+        &env,
+        &info.sender,
+        AdminAction::UpdateWhitelistContract {
+            contract: contract.clone(),
+        },
+        Some(&old_info),
+        Some(&contract_info),
+    )?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "update_whitelist_contract"),
+        ("contract", contract.as_str()),
+    ]))
+}
+
+fn execute_set_integration_quota(
+    deps: DepsMut,
+    info: MessageInfo,
+    contract: String,
+    budget_share: Option<Decimal>,
+    rate_limit: Option<RateLimit>,
+) -> Result<Response, ContractError> {
+    let contract = deps.api.addr_validate(&contract)?;
+    assert_role(deps.as_ref(), &info.sender, Role::WhitelistManager)?;
+
+    if !WHITELIST_CONTRACT.has(deps.storage, &contract) {
+        return Err(ContractError::NotWhitelisted {});
+    }
+    if budget_share
+        .map(|share| share > Decimal::one())
+        .unwrap_or(false)
+    {
+        return Err(ContractError::InvalidQuota {});
+    }
+
+    if budget_share.is_none() && rate_limit.is_none() {
+        INTEGRATION_QUOTA.remove(deps.storage, &contract);
+    } else {
+        INTEGRATION_QUOTA.save(
+            deps.storage,
+            &contract,
+            &IntegrationQuota {
+                budget_share,
+                rate_limit,
+            },
+        )?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "set_integration_quota"),
+        ("contract", contract.as_str()),
+    ]))
+}
+
+fn execute_remove_contract(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    contract: String,
+) -> Result<Response, ContractError> {
+    let contract = deps.api.addr_validate(&contract)?;
+    assert_role(deps.as_ref(), &info.sender, Role::WhitelistManager)?;
 
-    WHITELIST_CONTRACT.remove_hook(deps.storage, contract.clone())?;
+    let contract_info = WHITELIST_CONTRACT
+        .may_load(deps.storage, &contract)?
+        .ok_or(ContractError::NotWhitelisted {})?;
+    WHITELIST_CONTRACT.remove(deps.storage, &contract);
+    // a contract whitelisted again later starts without the old quota
+    INTEGRATION_QUOTA.remove(deps.storage, &contract);
+    append_admin_log(
+        deps.storage,
+        &env,
+        &info.sender,
+        AdminAction::RemoveContract {
+            contract: contract.clone(),
+        },
+        Some(&contract_info),
+        None,
+    )?;
 
     Ok(Response::new().add_attributes(vec![
         ("action", "remove_contract"),
@@ -146,6 +822,7 @@ fn execute_remove_contract(
     ]))
 }
 
+#[allow(clippy::too_many_arguments)]
 fn execute_create_campaign(
     deps: DepsMut,
     env: Env,
@@ -154,12 +831,11 @@ fn execute_create_campaign(
     end: u64,
     reward_token: AssetInfo,
     total_reward: Uint128,
+    require_solvency: bool,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    if config.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
-    }
+    assert_role(deps.as_ref(), &info.sender, Role::CampaignManager)?;
 
+    validate_asset_info(deps.api, &reward_token)?;
     if start > end {
         return Err(ContractError::InvalidCampaignTime {});
     }
@@ -174,19 +850,28 @@ fn execute_create_campaign(
     }
     let id = last_id + 1;
 
-    CAMPAIGN.save(
-        deps.storage,
+    let campaign = Campaign {
         id,
-        &Campaign {
-            id,
-            start,
-            end,
-            total_reward,
-            reward_token: reward_token.clone(),
-            distributed_amount: Uint128::zero(),
-        },
-    )?;
+        start,
+        end,
+        total_reward,
+        reward_token: reward_token.clone(),
+        distributed_amount: Uint128::zero(),
+        windows: vec![],
+    };
+    CAMPAIGN.save(deps.storage, id, &campaign)?;
     LAST_CAMPAIGN_ID.save(deps.storage, &id)?;
+    append_admin_log(
+        deps.storage,
+        &env,
+        &info.sender,
+        AdminAction::CreateCampaign { id },
+        None,
+        Some(&campaign),
+    )?;
+    if require_solvency {
+        assert_solvent(deps.as_ref(), &env, &campaign.reward_token)?;
+    }
 
     Ok(Response::new().add_attributes(vec![
         ("action", "create_campaign"),
@@ -198,6 +883,7 @@ fn execute_create_campaign(
     ]))
 }
 
+#[allow(clippy::too_many_arguments)]
 fn execute_edit_campaign(
     deps: DepsMut,
     env: Env,
@@ -206,54 +892,631 @@ fn execute_edit_campaign(
     start: Option<u64>,
     end: Option<u64>,
     total_reward: Option<Uint128>,
+    require_solvency: bool,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    if config.owner != info.sender {
-        return Err(ContractError::Unauthorized {});
-    }
+    assert_role(deps.as_ref(), &info.sender, Role::CampaignManager)?;
 
     let mut campaign = CAMPAIGN.load(deps.storage, id)?;
     if campaign.is_finished(&env) {
         return Err(ContractError::CampaignEnded {});
     }
+    let previous_end = match id {
+        1 => None,
+        _ => Some(CAMPAIGN.load(deps.storage, id - 1)?.end),
+    };
+
+    let before = campaign.clone();
+    campaign.edit(&env, previous_end, start, end, total_reward)?;
+    CAMPAIGN.save(deps.storage, id, &campaign)?;
 
-    if let Some(start) = start {
-        campaign.start = start;
+    // append to the edit history of the campaign
+    let index = CAMPAIGN_EDITS
+        .prefix(id)
+        .keys(deps.storage, None, None, Order::Descending)
+        .next()
+        .transpose()?
+        .map_or(0, |last| last + 1);
+    CAMPAIGN_EDITS.save(
+        deps.storage,
+        (id, index),
+        &CampaignEdit {
+            editor: info.sender.clone(),
+            time: env.block.time.seconds(),
+            start: (before.start, campaign.start),
+            end: (before.end, campaign.end),
+            total_reward: (before.total_reward, campaign.total_reward),
+        },
+    )?;
+    append_admin_log(
+        deps.storage,
+        &env,
+        &info.sender,
+        AdminAction::EditCampaign { id },
+        Some(&before),
+        Some(&campaign),
+    )?;
+    if require_solvency {
+        assert_solvent(deps.as_ref(), &env, &campaign.reward_token)?;
     }
-    if let Some(end) = end {
-        campaign.end = end;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "edit_campaign"),
+        ("campaign_id", &id.to_string()),
+    ]))
+}
+
+fn execute_add_campaign_window(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    window: MultiplierWindow,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::CampaignManager)?;
+
+    let mut campaign = CAMPAIGN.load(deps.storage, id)?;
+    if campaign.is_finished(&env) {
+        return Err(ContractError::CampaignEnded {});
     }
-    if let Some(total_reward) = total_reward {
-        campaign.total_reward = total_reward;
+    if window.start > window.end || window.start < campaign.start || window.end > campaign.end {
+        return Err(ContractError::InvalidWindow {});
     }
-    if campaign.start > campaign.end {
-        return Err(ContractError::InvalidCampaignTime {});
+    if let Some(other) = campaign
+        .windows
+        .iter()
+        .find(|other| other.start <= window.end && window.start <= other.end)
+    {
+        return Err(ContractError::WindowOverlap {
+            start: other.start,
+            end: other.end,
+        });
     }
 
+    campaign.windows.push(window.clone());
+    campaign.windows.sort_by_key(|window| window.start);
     CAMPAIGN.save(deps.storage, id, &campaign)?;
 
     Ok(Response::new().add_attributes(vec![
-        ("action", "edit_campaign"),
+        ("action", "add_campaign_window"),
         ("campaign_id", &id.to_string()),
+        ("start", &window.start.to_string()),
+        ("end", &window.end.to_string()),
+        ("multiplier", &window.multiplier.to_string()),
+    ]))
+}
+
+fn execute_remove_campaign_window(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
+    start: u64,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::CampaignManager)?;
+
+    let mut campaign = CAMPAIGN.load(deps.storage, id)?;
+    let index = campaign
+        .windows
+        .iter()
+        .position(|window| window.start == start)
+        .ok_or(ContractError::WindowNotFound {})?;
+    campaign.windows.remove(index);
+    CAMPAIGN.save(deps.storage, id, &campaign)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "remove_campaign_window"),
+        ("campaign_id", &id.to_string()),
+        ("start", &start.to_string()),
+    ]))
+}
+
+fn execute_set_campaign_multipliers(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
+    multipliers: CampaignMultipliersInfo,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::CampaignManager)?;
+
+    // campaign must exist
+    CAMPAIGN.load(deps.storage, id)?;
+    let multipliers = CampaignMultipliers {
+        contracts: multipliers
+            .contracts
+            .into_iter()
+            .map(|(contract, multiplier)| Ok((deps.api.addr_validate(&contract)?, multiplier)))
+            .collect::<StdResult<_>>()?,
+        tokens: multipliers.tokens,
+    };
+    validate_campaign_multipliers(&multipliers)?;
+    let tokens: Vec<AssetInfo> = multipliers
+        .tokens
+        .iter()
+        .map(|(token, _)| token.clone())
+        .collect();
+    validate_asset_infos(deps.api, &tokens)?;
+    CAMPAIGN_MULTIPLIERS.save(deps.storage, id, &multipliers)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "set_campaign_multipliers"),
+        ("campaign_id", &id.to_string()),
+    ]))
+}
+
+fn execute_set_campaign_bonus(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: u64,
+    bonus: Option<CampaignBonus>,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::CampaignManager)?;
+
+    // campaign must exist
+    CAMPAIGN.load(deps.storage, id)?;
+    match bonus {
+        Some(bonus) => {
+            if let Some(streak) = &bonus.streak {
+                if streak.days == 0 {
+                    return Err(ContractError::InvalidStreakBonus {});
+                }
+            }
+            CAMPAIGN_BONUS.save(deps.storage, id, &bonus)?;
+        }
+        None => CAMPAIGN_BONUS.remove(deps.storage, id),
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "set_campaign_bonus"),
+        ("campaign_id", &id.to_string()),
+    ]))
+}
+
+fn execute_set_user_boosts(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    boosts: Vec<UserBoostInfo>,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::CampaignManager)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    for UserBoostInfo { user, boost } in boosts.iter() {
+        let user = deps.api.addr_validate(user)?;
+        if boost.is_expired(&env) {
+            return Err(ContractError::InvalidBoostExpiry {});
+        }
+        if let BoostKind::Fixed { percent } = boost.kind {
+            if percent > config.max_cash_back_percent {
+                return Err(ContractError::PercentExceedsMax {
+                    percent,
+                    max: config.max_cash_back_percent,
+                });
+            }
+        }
+        USER_BOOST.save(deps.storage, &user, boost)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "set_user_boosts"),
+        ("count", &boosts.len().to_string()),
+    ]))
+}
+
+fn execute_remove_user_boosts(
+    deps: DepsMut,
+    info: MessageInfo,
+    users: Vec<String>,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::CampaignManager)?;
+
+    for user in users.iter() {
+        USER_BOOST.remove(deps.storage, &deps.api.addr_validate(user)?);
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "remove_user_boosts"),
+        ("count", &users.len().to_string()),
+    ]))
+}
+
+fn execute_exclude_users(
+    deps: DepsMut,
+    info: MessageInfo,
+    users: Vec<ExcludedUserInfo>,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::CampaignManager)?;
+
+    for ExcludedUserInfo { user, reason } in users.iter() {
+        EXCLUDED_USER.save(deps.storage, &deps.api.addr_validate(user)?, reason)?;
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "exclude_users"),
+        ("count", &users.len().to_string()),
+    ]))
+}
+
+fn execute_include_users(
+    deps: DepsMut,
+    info: MessageInfo,
+    users: Vec<String>,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::CampaignManager)?;
+
+    for user in users.iter() {
+        EXCLUDED_USER.remove(deps.storage, &deps.api.addr_validate(user)?);
+    }
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "include_users"),
+        ("count", &users.len().to_string()),
+    ]))
+}
+
+fn execute_set_nft_rules(
+    deps: DepsMut,
+    info: MessageInfo,
+    rules: Vec<NftRuleInfo>,
+) -> Result<Response, ContractError> {
+    assert_role(deps.as_ref(), &info.sender, Role::CampaignManager)?;
+    let config = CONFIG.load(deps.storage)?;
+
+    let rules = rules
+        .into_iter()
+        .map(|rule| {
+            Ok(NftRule {
+                collection: deps.api.addr_validate(&rule.collection)?,
+                min_tokens: rule.min_tokens,
+                bonus: rule.bonus,
+            })
+        })
+        .collect::<StdResult<Vec<NftRule>>>()?;
+    validate_nft_rules(&rules, config.max_cash_back_percent)?;
+    NFT_RULES.save(deps.storage, &rules)?;
+
+    Ok(Response::new().add_attributes(vec![
+        ("action", "set_nft_rules"),
+        ("count", &rules.len().to_string()),
     ]))
 }
 
 #[cfg_attr(not(feature = "library"), entry_point)]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::Config {} => to_json_binary(&CONFIG.load(deps.storage)?),
+        QueryMsg::Ownership {} => to_json_binary(&query_ownership(deps)?),
+        QueryMsg::PauseStatus {} => {
+            to_json_binary(&PAUSE_STATUS.may_load(deps.storage)?.unwrap_or_default())
+        }
+        QueryMsg::KeyMigration {} => to_json_binary(&KEY_MIGRATION.may_load(deps.storage)?),
+        QueryMsg::Roles { user } => to_json_binary(
+            &ROLES
+                .may_load(deps.storage, &deps.api.addr_validate(&user)?)?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::RoleMembers { start_after, limit } => {
+            to_json_binary(&query_role_members(deps, start_after, limit)?)
+        }
         QueryMsg::Campaign { id } => to_json_binary(&CAMPAIGN.load(deps.storage, id)?),
         QueryMsg::LastCampaign {} => to_json_binary(&query_last_campaign(deps)?),
         QueryMsg::LastCampaignId {} => to_json_binary(&LAST_CAMPAIGN_ID.load(deps.storage)?),
-        QueryMsg::WhitelistContract {} => {
-            to_json_binary(&WHITELIST_CONTRACT.query_hooks(deps)?.hooks)
+        QueryMsg::WhitelistContractInfo { contract } => to_json_binary(
+            &WHITELIST_CONTRACT.load(deps.storage, &deps.api.addr_validate(&contract)?)?,
+        ),
+        QueryMsg::WhitelistContracts { start_after, limit } => {
+            to_json_binary(&query_whitelist_contracts(deps, start_after, limit)?)
         }
+        QueryMsg::IntegrationUsages {
+            campaign_id,
+            start_after,
+            limit,
+        } => to_json_binary(&query_integration_usages(
+            deps,
+            campaign_id,
+            start_after,
+            limit,
+        )?),
         QueryMsg::PendingCashBack { user } => to_json_binary(
             &PENDING_CASH_BACK
-                .load(deps.storage, &user)
+                .may_load(deps.storage, &deps.api.addr_validate(&user)?)?
                 .unwrap_or_default(),
         ),
+        QueryMsg::UserBoost { user } => {
+            to_json_binary(&USER_BOOST.may_load(deps.storage, &deps.api.addr_validate(&user)?)?)
+        }
+        QueryMsg::UserBoosts { start_after, limit } => {
+            to_json_binary(&query_user_boosts(deps, start_after, limit)?)
+        }
+        QueryMsg::ExcludedUser { user } => {
+            to_json_binary(&EXCLUDED_USER.may_load(deps.storage, &deps.api.addr_validate(&user)?)?)
+        }
+        QueryMsg::ExcludedUsers { start_after, limit } => {
+            to_json_binary(&query_excluded_users(deps, start_after, limit)?)
+        }
+        QueryMsg::NftRules {} => {
+            to_json_binary(&NFT_RULES.may_load(deps.storage)?.unwrap_or_default())
+        }
+        QueryMsg::SimulateCashBack { user, tokens } => {
+            to_json_binary(&query_simulate_cash_back(deps, env, user, tokens)?)
+        }
+        QueryMsg::Solvency {} => to_json_binary(&query_solvency(deps, &env)?),
+        QueryMsg::PendingConfigChanges { start_after, limit } => {
+            to_json_binary(&query_pending_config_changes(deps, start_after, limit)?)
+        }
+        QueryMsg::AdminLog { start_after, limit } => {
+            to_json_binary(&query_admin_log(deps, start_after, limit)?)
+        }
+        QueryMsg::CampaignEdits {
+            id,
+            start_after,
+            limit,
+        } => to_json_binary(&query_campaign_edits(deps, id, start_after, limit)?),
+        QueryMsg::CampaignWindows { id } => {
+            to_json_binary(&CAMPAIGN.load(deps.storage, id)?.windows)
+        }
+        QueryMsg::CampaignMultipliers { id } => to_json_binary(
+            &CAMPAIGN_MULTIPLIERS
+                .may_load(deps.storage, id)?
+                .unwrap_or_default(),
+        ),
+        QueryMsg::EffectiveMultiplier { contract, token } => {
+            to_json_binary(&query_effective_multiplier(deps, contract, token)?)
+        }
+        QueryMsg::CampaignBonus { id } => {
+            to_json_binary(&CAMPAIGN_BONUS.may_load(deps.storage, id)?)
+        }
+        QueryMsg::UserBonuses { user } => to_json_binary(&query_user_bonuses(deps, env, user)?),
+        QueryMsg::UserCashBack { user, campaign_id } => {
+            to_json_binary(&query_user_cash_back(deps, user, campaign_id)?)
+        }
+        QueryMsg::UserHistory {
+            user,
+            start_after,
+            limit,
+        } => to_json_binary(&query_user_history(deps, user, start_after, limit)?),
+        QueryMsg::Referrer { user } => {
+            to_json_binary(&REFERRER.may_load(deps.storage, &deps.api.addr_validate(&user)?)?)
+        }
+        QueryMsg::Referees {
+            referrer,
+            start_after,
+            limit,
+        } => to_json_binary(&query_referees(deps, referrer, start_after, limit)?),
+        QueryMsg::PendingReferralReward { referrer } => {
+            to_json_binary(&read_pending_referral_rewards(
+                deps.storage,
+                Some(&deps.api.addr_validate(&referrer)?),
+            )?)
+        }
+    }
+}
+
+fn query_ownership(deps: Deps) -> StdResult<OwnershipResponse> {
+    let config = CONFIG.load(deps.storage)?;
+    let pending_owner = PENDING_OWNER.may_load(deps.storage)?;
+    Ok(OwnershipResponse {
+        owner: config.owner,
+        pending_owner: pending_owner.as_ref().map(|pending| pending.owner.clone()),
+        pending_expires: pending_owner.and_then(|pending| pending.expires),
+    })
+}
+
+fn query_role_members(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<RoleInfo>> {
+    let start_after = validate_optional_addr(deps.api, start_after)?;
+    Ok(read_roles(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|(user, roles)| RoleInfo { user, roles })
+        .collect())
+}
+
+fn query_whitelist_contracts(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<WhitelistContractResponse>> {
+    let start_after = validate_optional_addr(deps.api, start_after)?;
+    Ok(read_whitelist_contracts(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|(contract, info)| WhitelistContractResponse { contract, info })
+        .collect())
+}
+
+// campaign changes are written before this check, so the budget is counted as committed
+fn assert_solvent(deps: Deps, env: &Env, reward_token: &AssetInfo) -> Result<(), ContractError> {
+    if let Some(asset) = query_solvency(deps, env)?
+        .into_iter()
+        .find(|asset| &asset.info == reward_token && !asset.deficit.is_zero())
+    {
+        return Err(ContractError::Insolvent {
+            asset: asset.info.to_string(),
+            deficit: asset.deficit,
+        });
     }
+    Ok(())
+}
+
+fn query_solvency(deps: Deps, env: &Env) -> StdResult<Vec<AssetSolvency>> {
+    let mut liabilities: Vec<(AssetInfo, Uint128)> = vec![];
+    let mut add_liability = |info: &AssetInfo, amount: Uint128| -> StdResult<()> {
+        match liabilities.iter_mut().find(|(asset, _)| asset == info) {
+            Some((_, total)) => *total = total.checked_add(amount)?,
+            None => liabilities.push((info.clone(), amount)),
+        }
+        Ok(())
+    };
+
+    let last_id = LAST_CAMPAIGN_ID.may_load(deps.storage)?.unwrap_or_default();
+    if last_id > 0 {
+        // pending cash back is paid in the reward token of the last campaign
+        let reward_token = CAMPAIGN.load(deps.storage, last_id)?.reward_token;
+        let pending_cash_back = read_all_pending_cash_back(deps.storage)
+            .into_iter()
+            .try_fold(Uint128::zero(), |total, (_, amount)| {
+                total.checked_add(amount)
+            })?;
+        add_liability(&reward_token, pending_cash_back)?;
+    }
+    // referral rewards are paid in the reward token of the campaign they were earned in
+    for reward in read_pending_referral_rewards(deps.storage, None)? {
+        add_liability(&reward.info, reward.amount)?;
+    }
+    // budget left in running and upcoming campaigns, campaigns are sequential
+    for id in (1..=last_id).rev() {
+        let campaign = CAMPAIGN.load(deps.storage, id)?;
+        if campaign.is_finished(env) {
+            break;
+        }
+        add_liability(
+            &campaign.reward_token,
+            campaign
+                .total_reward
+                .saturating_sub(campaign.distributed_amount),
+        )?;
+    }
+
+    Ok(liabilities
+        .into_iter()
+        .map(|(info, liabilities)| {
+            let balance = query_asset_balance(&deps.querier, &env.contract.address, &info);
+            AssetSolvency {
+                surplus: balance.saturating_sub(liabilities),
+                deficit: liabilities.saturating_sub(balance),
+                info,
+                balance,
+                liabilities,
+            }
+        })
+        .collect())
+}
+
+fn query_pending_config_changes(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<PendingConfigChangeInfo>> {
+    Ok(
+        read_pending_config_changes(deps.storage, start_after, limit)?
+            .into_iter()
+            .map(|(id, change)| PendingConfigChangeInfo { id, change })
+            .collect(),
+    )
+}
+
+fn query_admin_log(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<AdminLogInfo>> {
+    Ok(read_admin_log(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|(index, entry)| AdminLogInfo { index, entry })
+        .collect())
+}
+
+fn query_campaign_edits(
+    deps: Deps,
+    id: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<CampaignEditInfo>> {
+    Ok(read_campaign_edits(deps.storage, id, start_after, limit)?
+        .into_iter()
+        .map(|(index, edit)| CampaignEditInfo { index, edit })
+        .collect())
+}
+
+fn query_integration_usages(
+    deps: Deps,
+    campaign_id: Option<u64>,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<IntegrationUsageResponse>> {
+    let start_after = validate_optional_addr(deps.api, start_after)?;
+    let campaign_id = match campaign_id {
+        Some(campaign_id) => campaign_id,
+        None => LAST_CAMPAIGN_ID.load(deps.storage)?,
+    };
+    read_integration_usages(deps.storage, campaign_id, start_after, limit)?
+        .into_iter()
+        .map(|(contract, usage)| {
+            Ok(IntegrationUsageResponse {
+                quota: INTEGRATION_QUOTA.may_load(deps.storage, &contract)?,
+                contract,
+                campaign_id,
+                usage,
+            })
+        })
+        .collect()
+}
+
+fn query_user_boosts(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<UserBoostInfo>> {
+    let start_after = validate_optional_addr(deps.api, start_after)?;
+    Ok(read_user_boosts(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|(user, boost)| UserBoostInfo {
+            user: user.to_string(),
+            boost,
+        })
+        .collect())
+}
+
+fn query_excluded_users(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<ExcludedUserInfo>> {
+    let start_after = validate_optional_addr(deps.api, start_after)?;
+    Ok(read_excluded_users(deps.storage, start_after, limit)?
+        .into_iter()
+        .map(|(user, reason)| ExcludedUserInfo {
+            user: user.to_string(),
+            reason,
+        })
+        .collect())
+}
+
+fn query_effective_multiplier(
+    deps: Deps,
+    contract: String,
+    token: AssetInfo,
+) -> StdResult<Decimal> {
+    let contract = deps.api.addr_validate(&contract)?;
+    let contract_info = WHITELIST_CONTRACT.load(deps.storage, &contract)?;
+    if !contract_info.is_fee_token_allowed(&token) {
+        return Ok(Decimal::zero());
+    }
+    let last_id = LAST_CAMPAIGN_ID.load(deps.storage)?;
+    let multipliers = CAMPAIGN_MULTIPLIERS
+        .may_load(deps.storage, last_id)?
+        .unwrap_or_default();
+
+    contract_info
+        .multiplier
+        .unwrap_or(Decimal::one())
+        .checked_mul(multipliers.contract_multiplier(&contract))?
+        .checked_mul(multipliers.token_multiplier(&token))
+        .map_err(StdError::from)
+}
+
+fn query_referees(
+    deps: Deps,
+    referrer: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<RefereeInfo>> {
+    let referrer = deps.api.addr_validate(&referrer)?;
+    let start_after = validate_optional_addr(deps.api, start_after)?;
+    Ok(read_referees(deps.storage, &referrer, start_after, limit)?
+        .into_iter()
+        .map(|(referee, earned)| RefereeInfo { referee, earned })
+        .collect())
 }
 
 fn query_last_campaign(deps: Deps) -> StdResult<Campaign> {
@@ -1,20 +1,163 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{CheckedMultiplyFractionError, Decimal, OverflowError, StdError, Uint128};
 use thiserror::Error;
 
+use crate::state::PausableFunction;
+
 #[derive(Error, Debug)]
 pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
-    #[error(transparent)]
-    HookError(#[from] cw_controllers::HookError),
+    #[error("Semver parsing error: {0}")]
+    SemVer(String),
+
+    #[error("{0}")]
+    Overflow(#[from] OverflowError),
+
+    #[error("{0}")]
+    CheckedMultiplyFraction(#[from] CheckedMultiplyFractionError),
 
     #[error("Unauthorized")]
     Unauthorized {},
 
+    #[error("Contract is already whitelisted")]
+    AlreadyWhitelisted {},
+
+    #[error("Contract is not whitelisted")]
+    NotWhitelisted {},
+
+    #[error("Contract is disabled")]
+    ContractDisabled {},
+
+    #[error("User is excluded from cash back: {reason}")]
+    UserExcluded { reason: String },
+
+    #[error("No cash back campaign")]
+    NoCampaign {},
+
+    #[error("Campaign is not in progress")]
+    CampaignNotInProgress {},
+
+    #[error("Campaign budget is exhausted")]
+    BudgetExhausted {},
+
+    #[error("Cash back percent is zero")]
+    ZeroCashBackPercent {},
+
+    #[error("Cash back amount is zero")]
+    ZeroCashBackAmount {},
+
+    #[error("Integration quota exceeded")]
+    QuotaExceeded {},
+
+    #[error("Budget share must be lte 1")]
+    InvalidQuota {},
+
+    #[error("Reported fees do not match the received funds")]
+    FeeMismatch {},
+
+    #[error("Fees are only accepted in verify fees mode")]
+    FeesNotVerified {},
+
+    #[error("Cw20 fee token is not allowed for this contract")]
+    Cw20FeeNotAllowed {},
+
+    #[error("Treasury is required to verify fees")]
+    TreasuryRequired {},
+
     #[error("Invalid campaign time range")]
     InvalidCampaignTime {},
 
     #[error("This campaign has ended")]
     CampaignEnded {},
+
+    #[error("Timelock must be zero or between {min} and {max} seconds")]
+    InvalidTimelock { min: u64, max: u64 },
+
+    #[error("Config change not found")]
+    ConfigChangeNotFound {},
+
+    #[error("Config change can not be executed before {execute_after}")]
+    ConfigChangeLocked { execute_after: u64 },
+
+    #[error("Contract balance of {asset} is short of its liabilities by {deficit}")]
+    Insolvent { asset: String, deficit: Uint128 },
+
+    #[error("Start of a started campaign can not be changed")]
+    CampaignAlreadyStarted {},
+
+    #[error("Campaign must start after the previous campaign ends")]
+    CampaignOverlap {},
+
+    #[error("Total reward must be gte distributed amount {distributed}")]
+    RewardBelowDistributed { distributed: Uint128 },
+
+    #[error("Duplicate cash back threshold: {threshold}")]
+    DuplicateThreshold { threshold: Uint128 },
+
+    #[error("Cash back percent must not decrease as threshold increases (threshold {threshold})")]
+    NonMonotonicRule { threshold: Uint128 },
+
+    #[error("Cash back percent {percent} exceeds max percent {max}")]
+    PercentExceedsMax { percent: Decimal, max: Decimal },
+
+    #[error("Max cash back percent must be lte 1")]
+    InvalidMaxPercent {},
+
+    #[error("Boost expiry must be in the future")]
+    InvalidBoostExpiry {},
+
+    #[error("Nft rule must require at least one token")]
+    InvalidNftRule {},
+
+    #[error("Referral percent must be lte 1")]
+    InvalidReferralPercent {},
+
+    #[error("Referrer has already been set")]
+    ReferrerAlreadySet {},
+
+    #[error("Cannot refer yourself")]
+    SelfReferral {},
+
+    #[error("Referral cycle detected")]
+    ReferralCycle {},
+
+    #[error("Nothing to claim")]
+    NothingToClaim {},
+
+    #[error("Streak bonus must require at least one day")]
+    InvalidStreakBonus {},
+
+    #[error("Multiplier window must be within the campaign time range")]
+    InvalidWindow {},
+
+    #[error("Multiplier window overlaps window {start} - {end}")]
+    WindowOverlap { start: u64, end: u64 },
+
+    #[error("Multiplier window not found")]
+    WindowNotFound {},
+
+    #[error("Duplicate multiplier for {key}")]
+    DuplicateMultiplier { key: String },
+
+    #[error("{function:?} are paused")]
+    Paused { function: PausableFunction },
+
+    #[error("No ownership proposal")]
+    NoOwnershipProposal {},
+
+    #[error("Ownership proposal has expired")]
+    OwnershipProposalExpired {},
+
+    #[error("Cannot migrate from contract {contract}")]
+    InvalidContractName { contract: String },
+
+    #[error("Cannot migrate from version {stored} to older version {new}")]
+    CannotDowngrade { stored: String, new: String },
+}
+
+impl From<semver::Error> for ContractError {
+    fn from(err: semver::Error) -> Self {
+        Self::SemVer(err.to_string())
+    }
 }
@@ -1,9 +1,181 @@
+use cosmwasm_schema::cw_serde;
 use cosmwasm_std::{
-    coin, to_json_binary, Addr, BankMsg, CosmosMsg, Decimal, QuerierWrapper, StdError, StdResult,
-    Uint128, WasmMsg,
+    coin, to_json_binary, Addr, Api, BankMsg, Coin, Coins, CosmosMsg, Decimal, QuerierWrapper,
+    StdResult, SubMsg, Uint128, WasmMsg,
 };
 use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg};
-use oraiswap::asset::AssetInfo;
+use oraiswap::asset::{Asset, AssetInfo};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    msg::{Cw20HookMsg, ExecuteMsg, QueryMsg, SimulateCashBackResponse},
+    state::{CampaignMultipliers, Config, NftBonus, NftRule},
+    ContractError,
+};
+
+/// CashBackContract is a wrapper around Addr that provides a lot of helpers
+/// for pair, router and other contracts integrating with cash back
+#[cw_serde]
+pub struct CashBackContract(pub Addr);
+
+impl CashBackContract {
+    pub fn addr(&self) -> Addr {
+        self.0.clone()
+    }
+
+    pub fn call<T: Into<ExecuteMsg>>(&self, msg: T) -> StdResult<CosmosMsg> {
+        self.call_with_funds(msg, vec![])
+    }
+
+    pub fn call_with_funds<T: Into<ExecuteMsg>>(
+        &self,
+        msg: T,
+        funds: Vec<Coin>,
+    ) -> StdResult<CosmosMsg> {
+        let msg = to_json_binary(&msg.into())?;
+        Ok(WasmMsg::Execute {
+            contract_addr: self.addr().into(),
+            msg,
+            funds,
+        }
+        .into())
+    }
+
+    /// Build message triggering cash back of `user` for reported `fees`,
+    /// used outside verify fees mode where the caller keeps the fees
+    pub fn trigger_msg(&self, user: &Addr, fees: Vec<Asset>) -> StdResult<CosmosMsg> {
+        self.call(ExecuteMsg::TriggerCashBack {
+            user: user.to_string(),
+            tokens: fees,
+            strict: None,
+        })
+    }
+
+    /// Build messages paying `fees` of `user` in verify fees mode: native fees are attached
+    /// as funds and every cw20 fee is sent through `Cw20ExecuteMsg::Send`
+    pub fn paid_trigger_msgs(&self, user: &Addr, fees: Vec<Asset>) -> StdResult<Vec<CosmosMsg>> {
+        self.paid_msgs(user, fees, None)
+    }
+
+    /// Same as `trigger_msg` but wrapped in a reply-on-error submessage,
+    /// so a cash back failure never reverts the caller's transaction.
+    /// With `strict`, skipped triggers fail too and reach the reply handler
+    pub fn trigger_sub_msg(
+        &self,
+        user: &Addr,
+        fees: Vec<Asset>,
+        strict: bool,
+        reply_id: u64,
+    ) -> StdResult<SubMsg> {
+        let msg = self.call(ExecuteMsg::TriggerCashBack {
+            user: user.to_string(),
+            tokens: fees,
+            strict: Some(strict),
+        })?;
+        Ok(SubMsg::reply_on_error(msg, reply_id))
+    }
+
+    /// Same as `paid_trigger_msgs` but wrapped in reply-on-error submessages
+    pub fn paid_trigger_sub_msgs(
+        &self,
+        user: &Addr,
+        fees: Vec<Asset>,
+        strict: bool,
+        reply_id: u64,
+    ) -> StdResult<Vec<SubMsg>> {
+        Ok(self
+            .paid_msgs(user, fees, Some(strict))?
+            .into_iter()
+            .map(|msg| SubMsg::reply_on_error(msg, reply_id))
+            .collect())
+    }
+
+    fn paid_msgs(
+        &self,
+        user: &Addr,
+        fees: Vec<Asset>,
+        strict: Option<bool>,
+    ) -> StdResult<Vec<CosmosMsg>> {
+        let mut funds = Coins::default();
+        let mut native_fees = vec![];
+        let mut msgs = vec![];
+        for fee in fees {
+            match &fee.info {
+                AssetInfo::NativeToken { denom } => {
+                    funds.add(coin(fee.amount.u128(), denom))?;
+                    native_fees.push(fee);
+                }
+                AssetInfo::Token { contract_addr } => {
+                    if fee.amount.is_zero() {
+                        continue;
+                    }
+                    msgs.push(
+                        WasmMsg::Execute {
+                            contract_addr: contract_addr.to_string(),
+                            msg: to_json_binary(&Cw20ExecuteMsg::Send {
+                                contract: self.addr().into(),
+                                amount: fee.amount,
+                                msg: to_json_binary(&Cw20HookMsg::TriggerCashBack {
+                                    user: user.to_string(),
+                                    strict,
+                                })?,
+                            })?,
+                            funds: vec![],
+                        }
+                        .into(),
+                    );
+                }
+            }
+        }
+        // native fees are reported in a single trigger
+        if !native_fees.is_empty() || msgs.is_empty() {
+            msgs.insert(
+                0,
+                self.call_with_funds(
+                    ExecuteMsg::TriggerCashBack {
+                        user: user.to_string(),
+                        tokens: native_fees,
+                        strict,
+                    },
+                    funds.into_vec(),
+                )?,
+            );
+        }
+        Ok(msgs)
+    }
+
+    fn query<T: DeserializeOwned>(&self, querier: &QuerierWrapper, msg: &QueryMsg) -> StdResult<T> {
+        querier.query_wasm_smart(self.addr(), msg)
+    }
+
+    pub fn config(&self, querier: &QuerierWrapper) -> StdResult<Config> {
+        self.query(querier, &QueryMsg::Config {})
+    }
+
+    pub fn pending_cash_back(&self, querier: &QuerierWrapper, user: &Addr) -> StdResult<Uint128> {
+        self.query(
+            querier,
+            &QueryMsg::PendingCashBack {
+                user: user.to_string(),
+            },
+        )
+    }
+
+    pub fn simulate_cash_back(
+        &self,
+        querier: &QuerierWrapper,
+        user: &Addr,
+        fees: Vec<Asset>,
+    ) -> StdResult<SimulateCashBackResponse> {
+        self.query(
+            querier,
+            &QueryMsg::SimulateCashBack {
+                user: user.to_string(),
+                tokens: fees,
+            },
+        )
+    }
+}
 
 pub fn build_transfer_msg(
     token: &AssetInfo,
@@ -54,12 +226,185 @@ pub fn query_asset_balance(querier: &QuerierWrapper, user: &Addr, token: &AssetI
     }
 }
 
-pub fn validate_cash_back_rule(rules: &[(Uint128, Decimal)]) -> StdResult<()> {
-    if rules
-        .iter()
-        .any(|&(_, percent)| percent.gt(&Decimal::one()))
-    {
-        return Err(StdError::generic_err("Cash back percent must be lte 1"));
+// subset of cw721 query interface used to check holders
+#[cw_serde]
+enum Cw721QueryMsg {
+    Tokens {
+        owner: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+struct TokensResponse {
+    tokens: Vec<String>,
+}
+
+// returns true if user owns at least `min_tokens` tokens of the collection,
+// tokens are paged through as collections cap the page size
+pub fn query_nft_holder(
+    querier: &QuerierWrapper,
+    user: &Addr,
+    collection: &Addr,
+    min_tokens: u32,
+) -> bool {
+    let mut owned = 0u32;
+    let mut start_after = None;
+    while owned < min_tokens {
+        let res: TokensResponse = match querier.query_wasm_smart(
+            collection.to_string(),
+            &Cw721QueryMsg::Tokens {
+                owner: user.to_string(),
+                start_after,
+                limit: Some(min_tokens - owned),
+            },
+        ) {
+            Ok(res) => res,
+            Err(_) => return false,
+        };
+        let Some(last) = res.tokens.last() else {
+            return false;
+        };
+        owned = owned.saturating_add(res.tokens.len() as u32);
+        start_after = Some(last.clone());
+    }
+    true
+}
+
+pub fn validate_nft_rules(rules: &[NftRule], max_percent: Decimal) -> Result<(), ContractError> {
+    for rule in rules {
+        if rule.min_tokens == 0 {
+            return Err(ContractError::InvalidNftRule {});
+        }
+        let percent = match rule.bonus {
+            NftBonus::MinPercent { percent } => percent,
+            NftBonus::AddPercent { percent } => percent,
+        };
+        if percent > max_percent {
+            return Err(ContractError::PercentExceedsMax {
+                percent,
+                max: max_percent,
+            });
+        }
+    }
+    Ok(())
+}
+
+// default upper bound of cash back percent, used at instantiate and for migrated configs
+pub const DEFAULT_MAX_CASH_BACK_PERCENT: u64 = 50;
+
+// a max of 100% is allowed, so a rule may give back the whole fee
+pub fn validate_max_cash_back_percent(max_percent: Decimal) -> Result<(), ContractError> {
+    if max_percent > Decimal::one() {
+        return Err(ContractError::InvalidMaxPercent {});
+    }
+    Ok(())
+}
+
+// verified fees are forwarded to the treasury, so they never mix with the reward balance
+pub fn validate_fee_settings(
+    verify_fees: bool,
+    treasury: Option<&Addr>,
+) -> Result<(), ContractError> {
+    if verify_fees && treasury.is_none() {
+        return Err(ContractError::TreasuryRequired {});
+    }
+    Ok(())
+}
+
+pub fn validate_optional_addr(api: &dyn Api, addr: Option<String>) -> StdResult<Option<Addr>> {
+    addr.map(|addr| api.addr_validate(&addr)).transpose()
+}
+
+// contract address of a cw20 token must be valid
+pub fn validate_asset_info(api: &dyn Api, info: &AssetInfo) -> StdResult<()> {
+    if let AssetInfo::Token { contract_addr } = info {
+        api.addr_validate(contract_addr.as_str())?;
+    }
+    Ok(())
+}
+
+pub fn validate_asset_infos(api: &dyn Api, infos: &[AssetInfo]) -> StdResult<()> {
+    for info in infos {
+        validate_asset_info(api, info)?;
+    }
+    Ok(())
+}
+
+pub fn validate_campaign_multipliers(
+    multipliers: &CampaignMultipliers,
+) -> Result<(), ContractError> {
+    for (index, (contract, _)) in multipliers.contracts.iter().enumerate() {
+        if multipliers.contracts[..index]
+            .iter()
+            .any(|(other, _)| other == contract)
+        {
+            return Err(ContractError::DuplicateMultiplier {
+                key: contract.to_string(),
+            });
+        }
+    }
+    for (index, (token, _)) in multipliers.tokens.iter().enumerate() {
+        if multipliers.tokens[..index]
+            .iter()
+            .any(|(other, _)| other == token)
+        {
+            return Err(ContractError::DuplicateMultiplier {
+                key: token.to_string(),
+            });
+        }
+    }
+    Ok(())
+}
+
+pub fn validate_referral_percent(referral_percent: Decimal) -> Result<(), ContractError> {
+    if referral_percent > Decimal::one() {
+        return Err(ContractError::InvalidReferralPercent {});
+    }
+    Ok(())
+}
+
+// shortest delay of timelocked config changes, zero disables the timelock
+pub const MIN_TIMELOCK: u64 = 86400;
+// longest delay, so lowering a timelock set by mistake never takes longer than this
+pub const MAX_TIMELOCK: u64 = 30 * 86400;
+
+pub fn validate_timelock(timelock: u64) -> Result<(), ContractError> {
+    if timelock != 0 && !(MIN_TIMELOCK..=MAX_TIMELOCK).contains(&timelock) {
+        return Err(ContractError::InvalidTimelock {
+            min: MIN_TIMELOCK,
+            max: MAX_TIMELOCK,
+        });
+    }
+    Ok(())
+}
+
+// rules can be passed in any order: thresholds must be unique and a higher threshold must not give a lower percent
+pub fn validate_cash_back_rule(
+    rules: &[(Uint128, Decimal)],
+    max_percent: Decimal,
+) -> Result<(), ContractError> {
+    let mut sorted = rules.to_vec();
+    sorted.sort_by_key(|rule| rule.0);
+
+    for (index, &(threshold, percent)) in sorted.iter().enumerate() {
+        if percent > max_percent {
+            return Err(ContractError::PercentExceedsMax {
+                percent,
+                max: max_percent,
+            });
+        }
+        if index == 0 {
+            continue;
+        }
+        let (prev_threshold, prev_percent) = sorted[index - 1];
+        if prev_threshold == threshold {
+            return Err(ContractError::DuplicateThreshold { threshold });
+        }
+        if prev_percent > percent {
+            return Err(ContractError::NonMonotonicRule { threshold });
+        }
     }
     Ok(())
 }
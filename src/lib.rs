@@ -2,6 +2,7 @@ pub mod cash_back;
 pub mod contract;
 mod error;
 pub mod helpers;
+pub mod migration;
 pub mod msg;
 pub mod state;
 
@@ -0,0 +1,214 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{Addr, Api, Decimal, DepsMut, Empty, Order, StdError, StdResult, Uint128};
+use cw_storage_plus::{Bound, Item};
+use oraiswap::asset::AssetInfo;
+use semver::Version;
+
+use crate::helpers::DEFAULT_MAX_CASH_BACK_PERCENT;
+use crate::state::{
+    Campaign, Config, ContractKind, KeyMigration, WhitelistContractInfo, CAMPAIGN, CONFIG,
+    KEY_MIGRATION, PENDING_CASH_BACK, USER_CAMPAIGNS, USER_CASH_BACK, WHITELIST_CONTRACT,
+};
+
+// version of contracts deployed before cw2 info was stored
+pub const LEGACY_VERSION: &str = "0.1.0";
+
+type Migration = fn(DepsMut) -> StdResult<()>;
+
+// user keys normalized per MigrateKeys call
+const DEFAULT_KEY_LIMIT: u32 = 100;
+const MAX_KEY_LIMIT: u32 = 500;
+
+// (version, migration) sorted by version, every migration newer than the stored version is run
+const MIGRATIONS: &[(&str, Migration)] = &[("0.2.0", migrate_v0_2_0)];
+
+// storage layouts of 0.1.0
+#[cw_serde]
+struct ConfigV0_1 {
+    pub owner: Addr,
+    pub underlying_token: AssetInfo,
+    pub rules: Vec<(Uint128, Decimal)>,
+}
+
+const CONFIG_V0_1: Item<ConfigV0_1> = Item::new("config");
+// whitelisted contracts used to be stored as cw-controllers hooks
+const WHITELIST_CONTRACT_V0_1: Item<Vec<Addr>> = Item::new("whitelist_contract");
+
+// run all migrations after the stored version, returns the versions migrated to
+pub fn migrate_state(mut deps: DepsMut, stored: &Version) -> StdResult<Vec<&'static str>> {
+    let mut migrated = vec![];
+    for (version, migration) in MIGRATIONS {
+        let target =
+            Version::parse(version).map_err(|err| StdError::generic_err(err.to_string()))?;
+        if target > *stored {
+            migration(deps.branch())?;
+            migrated.push(*version);
+        }
+    }
+    Ok(migrated)
+}
+
+fn migrate_v0_2_0(deps: DepsMut) -> StdResult<()> {
+    // addresses used to be taken from messages without validation, store them normalized
+    let api = deps.api;
+    let storage = deps.storage;
+
+    // config gained the rule mode, fee verification and referral settings
+    if CONFIG.load(storage).is_err() {
+        let config = CONFIG_V0_1.load(storage)?;
+        // legacy rules could go up to 100%, the max is raised so they stay valid
+        let max_cash_back_percent = config.rules.iter().map(|(_, percent)| *percent).fold(
+            Decimal::percent(DEFAULT_MAX_CASH_BACK_PERCENT),
+            Decimal::max,
+        );
+        CONFIG.save(
+            storage,
+            &Config {
+                owner: Some(normalize_addr(api, config.owner)),
+                underlying_token: normalize_asset_info(api, config.underlying_token),
+                rules: config.rules,
+                max_cash_back_percent,
+                rule_mode: Default::default(),
+                verify_fees: false,
+                treasury: None,
+                referral_percent: Decimal::zero(),
+                timelock: 0,
+            },
+        )?;
+    }
+
+    // whitelisted contracts moved from a list to a map with metadata
+    for contract in WHITELIST_CONTRACT_V0_1
+        .may_load(storage)?
+        .unwrap_or_default()
+    {
+        let contract = normalize_addr(api, contract);
+        if !WHITELIST_CONTRACT.has(storage, &contract) {
+            WHITELIST_CONTRACT.save(
+                storage,
+                &contract,
+                &WhitelistContractInfo {
+                    label: String::new(),
+                    kind: ContractKind::default(),
+                    multiplier: None,
+                    allowed_fee_tokens: vec![],
+                    enabled: true,
+                },
+            )?;
+        }
+    }
+    WHITELIST_CONTRACT_V0_1.remove(storage);
+
+    // campaigns gained multiplier windows, rewrite them in the new layout
+    let campaigns = CAMPAIGN
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<(u64, Campaign)>>>()?;
+    for (id, mut campaign) in campaigns {
+        campaign.reward_token = normalize_asset_info(api, campaign.reward_token);
+        CAMPAIGN.save(storage, id, &campaign)?;
+    }
+
+    // maps keyed by user can be large, they are normalized in batches with MigrateKeys
+    KEY_MIGRATION.save(
+        storage,
+        &KeyMigration::PendingCashBack { start_after: None },
+    )?;
+
+    Ok(())
+}
+
+// normalize up to `limit` user keys, amounts of the same user are merged.
+// A normalized key never sorts before the key it replaces, so it is visited later in the pass
+pub fn migrate_keys(deps: DepsMut, limit: Option<u32>) -> StdResult<Option<KeyMigration>> {
+    let api = deps.api;
+    let storage = deps.storage;
+    let mut left = limit.unwrap_or(DEFAULT_KEY_LIMIT).min(MAX_KEY_LIMIT) as usize;
+    let mut step = KEY_MIGRATION.may_load(storage)?;
+
+    while left > 0 {
+        step = match step {
+            None => break,
+            Some(KeyMigration::PendingCashBack { start_after }) => {
+                let entries = PENDING_CASH_BACK
+                    .range(
+                        storage,
+                        start_after.as_ref().map(Bound::exclusive),
+                        None,
+                        Order::Ascending,
+                    )
+                    .take(left)
+                    .collect::<StdResult<Vec<(Addr, Uint128)>>>()?;
+                let done = entries.len() < left;
+                left -= entries.len();
+                let last = entries.last().map(|(user, _)| user.clone());
+                for (user, amount) in entries {
+                    let normalized = normalize_addr(api, user.clone());
+                    if normalized == user {
+                        continue;
+                    }
+                    PENDING_CASH_BACK.remove(storage, &user);
+                    PENDING_CASH_BACK.update(storage, &normalized, |existing| {
+                        Ok::<_, StdError>(existing.unwrap_or_default().checked_add(amount)?)
+                    })?;
+                }
+                Some(match done {
+                    true => KeyMigration::UserCashBack { start_after: None },
+                    false => KeyMigration::PendingCashBack { start_after: last },
+                })
+            }
+            Some(KeyMigration::UserCashBack { start_after }) => {
+                let entries = USER_CASH_BACK
+                    .range(
+                        storage,
+                        start_after
+                            .as_ref()
+                            .map(|(id, user)| Bound::exclusive((*id, user))),
+                        None,
+                        Order::Ascending,
+                    )
+                    .take(left)
+                    .collect::<StdResult<Vec<((u64, Addr), Uint128)>>>()?;
+                let done = entries.len() < left;
+                left -= entries.len();
+                let last = entries.last().map(|(key, _)| key.clone());
+                for ((id, user), amount) in entries {
+                    let normalized = normalize_addr(api, user.clone());
+                    if normalized == user {
+                        // the user index of cash back earned per campaign is built along
+                        USER_CAMPAIGNS.save(storage, (&user, id), &Empty {})?;
+                        continue;
+                    }
+                    USER_CASH_BACK.remove(storage, (id, &user));
+                    USER_CASH_BACK.update(storage, (id, &normalized), |existing| {
+                        Ok::<_, StdError>(existing.unwrap_or_default().checked_add(amount)?)
+                    })?;
+                }
+                match done {
+                    true => None,
+                    false => Some(KeyMigration::UserCashBack { start_after: last }),
+                }
+            }
+        };
+    }
+
+    match &step {
+        Some(step) => KEY_MIGRATION.save(storage, step)?,
+        None => KEY_MIGRATION.remove(storage),
+    }
+    Ok(step)
+}
+
+// lowercase an address, kept as is if it is still invalid
+fn normalize_addr(api: &dyn Api, addr: Addr) -> Addr {
+    api.addr_validate(&addr.as_str().to_lowercase())
+        .unwrap_or(addr)
+}
+
+fn normalize_asset_info(api: &dyn Api, info: AssetInfo) -> AssetInfo {
+    match info {
+        AssetInfo::Token { contract_addr } => AssetInfo::Token {
+            contract_addr: normalize_addr(api, contract_addr),
+        },
+        native => native,
+    }
+}
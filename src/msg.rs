@@ -1,30 +1,100 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
 use cosmwasm_std::{Addr, Decimal, Uint128};
+use cw20::Cw20ReceiveMsg;
 use oraiswap::asset::{Asset, AssetInfo};
 
-use crate::state::{Campaign, Config};
+use crate::state::{
+    AdminLogEntry, Campaign, CampaignBonus, CampaignEdit, CampaignMultipliers, Config,
+    ContractKind, IntegrationQuota, IntegrationUsage, KeyMigration, MultiplierWindow, NftBonus,
+    NftRule, PausableFunction, PauseStatus, PendingConfigChange, RateLimit, Role, RuleMode,
+    UserBoost, WhitelistContractInfo,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
-    pub owner: Addr,
+    pub owner: String,
     pub underlying_token: AssetInfo,
     pub rules: Option<Vec<(Uint128, Decimal)>>, // contain list conditions: balance - % cash back
+    pub max_cash_back_percent: Option<Decimal>, // default 50%
+    pub rule_mode: Option<RuleMode>,            // default Step
+    pub verify_fees: Option<bool>,              // default false
+    pub treasury: Option<String>,
+    pub referral_percent: Option<Decimal>, // default 0, no referral reward
+    pub timelock: Option<u64>,             // default 0, config changes apply at once
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
+    // with a timelock, the change is queued and applied by ExecuteConfigChange after the delay
     UpdateConfig {
-        owner: Option<Addr>,
         underlying_token: Option<AssetInfo>,
         rules: Option<Vec<(Uint128, Decimal)>>,
+        max_cash_back_percent: Option<Decimal>,
+        rule_mode: Option<RuleMode>,
+        verify_fees: Option<bool>,
+        treasury: Option<String>,
+        referral_percent: Option<Decimal>,
+        timelock: Option<u64>, // delay in seconds, zero disables the timelock
+    },
+    // apply a queued config change once its delay has passed, callable by anyone
+    // as long as the proposer still holds the roles the change requires
+    ExecuteConfigChange {
+        id: u64,
+    },
+    CancelConfigChange {
+        id: u64,
+    },
+    // propose a new owner, who must accept before `expires` (timestamp in seconds)
+    ProposeOwner {
+        owner: String,
+        expires: Option<u64>,
+    },
+    AcceptOwnership {},
+    CancelOwnershipProposal {},
+    // remove the owner for good, admin grants are dropped with it
+    RenounceOwnership {},
+    // grant a role to a user, admin only
+    GrantRole {
+        user: String,
+        role: Role,
+    },
+    RevokeRole {
+        user: String,
+        role: Role,
+    },
+    // halt one function, or every function if none, guardian only
+    Pause {
+        function: Option<PausableFunction>,
+    },
+    Unpause {
+        function: Option<PausableFunction>,
     },
     // Allow only whitelisted contracts to trigger cash back
     WhitelistContract {
-        contract: Addr,
+        contract: String,
+        label: Option<String>,
+        kind: Option<ContractKind>,
+        multiplier: Option<Decimal>,
+        allowed_fee_tokens: Option<Vec<AssetInfo>>,
+    },
+    // update metadata of a whitelisted contract
+    UpdateWhitelistContract {
+        contract: String,
+        label: Option<String>,
+        kind: Option<ContractKind>,
+        multiplier: Option<Decimal>,
+        allowed_fee_tokens: Option<Vec<AssetInfo>>,
+        enabled: Option<bool>,
+    },
+    // set budget share and rate limit of a whitelisted contract, remove quota if both are none
+    SetIntegrationQuota {
+        contract: String,
+        budget_share: Option<Decimal>,
+        rate_limit: Option<RateLimit>,
     },
     // Exclude contracts that are eligible for cash back
     RemoveContract {
-        contract: Addr,
+        contract: String,
     },
     // create cash back campaign
     CreateCampaign {
@@ -32,6 +102,7 @@ pub enum ExecuteMsg {
         end: u64,
         reward_token: AssetInfo,
         total_reward: Uint128,
+        require_solvency: Option<bool>, // refuse if the contract can not cover its liabilities
     },
     // edit campaign
     EditCampaign {
@@ -39,14 +110,77 @@ pub enum ExecuteMsg {
         start: Option<u64>,
         end: Option<u64>,
         total_reward: Option<Uint128>,
+        require_solvency: Option<bool>,
     },
     // called by a whitelisted contract, this function triggers a cashback for the user
+    // in verify fees mode, native fee tokens must be attached as funds
+    // in strict mode, return an error instead of skipping when no cash back is accrued
     TriggerCashBack {
-        user: Addr,
+        user: String,
         tokens: Vec<Asset>,
+        strict: Option<bool>,
+    },
+    // trigger cash back with cw20 fee token sent by a whitelisted contract
+    Receive(Cw20ReceiveMsg),
+    // add or replace boosts of users
+    SetUserBoosts {
+        boosts: Vec<UserBoostInfo>,
+    },
+    RemoveUserBoosts {
+        users: Vec<String>,
+    },
+    // exclude users (bots, team wallets, sybils) from cash back
+    ExcludeUsers {
+        users: Vec<ExcludedUserInfo>,
+    },
+    IncludeUsers {
+        users: Vec<String>,
+    },
+    // replace bonus rules for nft holders
+    SetNftRules {
+        rules: Vec<NftRuleInfo>,
+    },
+    // add a happy hour window with a cash back multiplier to a campaign
+    AddCampaignWindow {
+        id: u64,
+        window: MultiplierWindow,
+    },
+    // remove the window starting at `start` from a campaign
+    RemoveCampaignWindow {
+        id: u64,
+        start: u64,
+    },
+    // replace multipliers per triggering contract and fee token of a campaign
+    SetCampaignMultipliers {
+        id: u64,
+        multipliers: CampaignMultipliersInfo,
     },
+    // set retention bonuses of a campaign, remove them if none
+    SetCampaignBonus {
+        id: u64,
+        bonus: Option<CampaignBonus>,
+    },
+    // register referrer of the sender, can only be set once
+    SetReferrer {
+        referrer: String,
+    },
+    // claim pending referral rewards of the sender, each in the reward token of its campaign
+    ClaimReferralReward {},
+    // distribute all pending cash back, keeper only
     // TODO: Move to Sudo entrypoint
     CashBack {},
+    // normalize up to `limit` user keys left by migrate, admin only
+    MigrateKeys {
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub struct MigrateMsg {}
+
+#[cw_serde]
+pub enum Cw20HookMsg {
+    TriggerCashBack { user: String, strict: Option<bool> },
 }
 
 #[cw_serde]
@@ -54,14 +188,219 @@ pub enum ExecuteMsg {
 pub enum QueryMsg {
     #[returns(Config)]
     Config {},
+    #[returns(OwnershipResponse)]
+    Ownership {},
+    #[returns(PauseStatus)]
+    PauseStatus {},
+    // user keys still to normalize after migrate, none when done
+    #[returns(Option<KeyMigration>)]
+    KeyMigration {},
+    #[returns(Vec<Role>)]
+    Roles { user: String },
+    #[returns(Vec<RoleInfo>)]
+    RoleMembers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // contract balance against pending payouts and unspent budgets of running campaigns, per asset
+    #[returns(Vec<AssetSolvency>)]
+    Solvency {},
+    #[returns(Vec<PendingConfigChangeInfo>)]
+    PendingConfigChanges {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    // admin actions, oldest first
+    #[returns(Vec<AdminLogInfo>)]
+    AdminLog {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
     #[returns(Campaign)]
     Campaign { id: u64 },
     #[returns(Campaign)]
     LastCampaign {},
     #[returns(u64)]
     LastCampaignId {},
-    #[returns(Vec<String>)]
-    WhitelistContract {},
+    #[returns(WhitelistContractInfo)]
+    WhitelistContractInfo { contract: String },
+    #[returns(Vec<WhitelistContractResponse>)]
+    WhitelistContracts {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(Vec<IntegrationUsageResponse>)]
+    IntegrationUsages {
+        campaign_id: Option<u64>, // default last campaign
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
     #[returns(Uint128)]
-    PendingCashBack { user: Addr },
+    PendingCashBack { user: String },
+    // cash back of the user in a campaign
+    #[returns(UserCashBackResponse)]
+    UserCashBack { user: String, campaign_id: u64 },
+    // cash back of the user in every campaign with earnings, oldest first
+    #[returns(Vec<UserCashBackResponse>)]
+    UserHistory {
+        user: String,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(Option<UserBoost>)]
+    UserBoost { user: String },
+    #[returns(Vec<UserBoostInfo>)]
+    UserBoosts {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(Option<String>)]
+    ExcludedUser { user: String },
+    #[returns(Vec<ExcludedUserInfo>)]
+    ExcludedUsers {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    #[returns(Vec<NftRule>)]
+    NftRules {},
+    #[returns(SimulateCashBackResponse)]
+    SimulateCashBack { user: String, tokens: Vec<Asset> },
+    // edit history of a campaign, oldest first
+    #[returns(Vec<CampaignEditInfo>)]
+    CampaignEdits {
+        id: u64,
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    #[returns(Vec<MultiplierWindow>)]
+    CampaignWindows { id: u64 },
+    #[returns(CampaignMultipliers)]
+    CampaignMultipliers { id: u64 },
+    // multiplier applied to the cash back percent of a fee token in the last campaign
+    #[returns(Decimal)]
+    EffectiveMultiplier { contract: String, token: AssetInfo },
+    #[returns(Option<CampaignBonus>)]
+    CampaignBonus { id: u64 },
+    #[returns(UserBonusesResponse)]
+    UserBonuses { user: String },
+    #[returns(Option<Addr>)]
+    Referrer { user: String },
+    #[returns(Vec<RefereeInfo>)]
+    Referees {
+        referrer: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    // pending referral rewards summed per reward token
+    #[returns(Vec<Asset>)]
+    PendingReferralReward { referrer: String },
+}
+
+#[cw_serde]
+pub struct SimulateCashBackResponse {
+    pub cash_back_percent: Decimal,
+    pub cash_back_amount: Uint128, // in reward token of the running campaign, zero if none
+}
+
+#[cw_serde]
+pub struct UserCashBackResponse {
+    pub campaign_id: u64,
+    pub reward_token: AssetInfo, // reward token of the campaign, earned amounts are in it
+    pub earned: Uint128,
+    pub claimed: Uint128, // paid out by a distribution
+    pub pending: Uint128, // waiting for the next distribution
+}
+
+#[cw_serde]
+pub struct UserBoostInfo {
+    pub user: String,
+    pub boost: UserBoost,
+}
+
+#[cw_serde]
+pub struct ExcludedUserInfo {
+    pub user: String,
+    pub reason: String,
+}
+
+#[cw_serde]
+pub struct NftRuleInfo {
+    pub collection: String,
+    pub min_tokens: u32,
+    pub bonus: NftBonus,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct CampaignMultipliersInfo {
+    pub contracts: Vec<(String, Decimal)>,
+    pub tokens: Vec<(AssetInfo, Decimal)>,
+}
+
+#[cw_serde]
+pub struct WhitelistContractResponse {
+    pub contract: Addr,
+    pub info: WhitelistContractInfo,
+}
+
+#[cw_serde]
+pub struct IntegrationUsageResponse {
+    pub contract: Addr,
+    pub campaign_id: u64,
+    pub quota: Option<IntegrationQuota>,
+    pub usage: IntegrationUsage,
+}
+
+#[cw_serde]
+pub struct AssetSolvency {
+    pub info: AssetInfo,
+    pub balance: Uint128,
+    pub liabilities: Uint128,
+    pub surplus: Uint128, // zero if in deficit
+    pub deficit: Uint128, // zero if solvent
+}
+
+#[cw_serde]
+pub struct PendingConfigChangeInfo {
+    pub id: u64,
+    pub change: PendingConfigChange,
+}
+
+#[cw_serde]
+pub struct AdminLogInfo {
+    pub index: u64,
+    pub entry: AdminLogEntry,
+}
+
+#[cw_serde]
+pub struct CampaignEditInfo {
+    pub index: u64,
+    pub edit: CampaignEdit,
+}
+
+#[cw_serde]
+pub struct RefereeInfo {
+    pub referee: Addr,
+    pub earned: Uint128, // total referral reward earned from this referee
+}
+
+#[cw_serde]
+pub struct UserBonusesResponse {
+    pub campaign_id: u64,
+    pub first_trade_bonus: Uint128, // zero if the user already traded in this campaign
+    pub streak_days: u32,           // streak length if the user trades today
+    pub streak_multiplier: Option<Decimal>, // multiplier applied if the user trades today
+}
+
+#[cw_serde]
+pub struct OwnershipResponse {
+    pub owner: Option<Addr>,
+    pub pending_owner: Option<Addr>,
+    pub pending_expires: Option<u64>,
+}
+
+#[cw_serde]
+pub struct RoleInfo {
+    pub user: Addr,
+    pub roles: Vec<Role>,
 }
@@ -1,25 +1,372 @@
 use cosmwasm_schema::cw_serde;
-use cosmwasm_std::{Addr, Decimal, Env, Order, Storage, Uint128};
-use cw_controllers::Hooks;
-use cw_storage_plus::{Item, Map};
-use oraiswap::asset::AssetInfo;
+use cosmwasm_std::{to_json_string, Addr, Decimal, Empty, Env, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::{Bound, Item, Map};
+use oraiswap::asset::{Asset, AssetInfo};
+use serde::Serialize;
 
-pub const WHITELIST_CONTRACT: Hooks = Hooks::new("whitelist_contract");
+use crate::ContractError;
+
+// contracts allowed to trigger cash back, mapping from contract -> metadata
+pub const WHITELIST_CONTRACT: Map<&Addr, WhitelistContractInfo> =
+    Map::new("whitelist_contract_info");
 pub const CONFIG: Item<Config> = Item::new("config");
+// roles granted to users, the owner implicitly has every role
+pub const ROLES: Map<&Addr, Vec<Role>> = Map::new("roles");
+// functions halted by the circuit breaker
+pub const PAUSE_STATUS: Item<PauseStatus> = Item::new("pause_status");
+// owner proposed by the current owner, waiting for acceptance
+pub const PENDING_OWNER: Item<PendingOwner> = Item::new("pending_owner");
 // campaign detail
 pub const CAMPAIGN: Map<u64, Campaign> = Map::new("campaign");
+// mapping from (campaignId, index) -> edit applied to the campaign, in order
+pub const CAMPAIGN_EDITS: Map<(u64, u64), CampaignEdit> = Map::new("campaign_edits");
 // last campaign id
 pub const LAST_CAMPAIGN_ID: Item<u64> = Item::new("last_campaign_id");
 // store pending cash back amount
 pub const PENDING_CASH_BACK: Map<&Addr, Uint128> = Map::new("pending_cash_back");
 // mapping from (campaignId, user) -> total amount cash back in this campaign
 pub const USER_CASH_BACK: Map<(u64, &Addr), Uint128> = Map::new("user_cash_back");
+// index of USER_CASH_BACK by user, mapping from (user, campaignId) -> empty
+pub const USER_CAMPAIGNS: Map<(&Addr, u64), Empty> = Map::new("user_campaigns");
+// owner-managed boost per user (VIP / partner lists)
+pub const USER_BOOST: Map<&Addr, UserBoost> = Map::new("user_boost");
+// users excluded from cash back, mapping from user -> reason
+pub const EXCLUDED_USER: Map<&Addr, String> = Map::new("excluded_user");
+// budget share and rate limit per whitelisted contract
+pub const INTEGRATION_QUOTA: Map<&Addr, IntegrationQuota> = Map::new("integration_quota");
+// mapping from (campaignId, contract) -> cash back accrued through this contract
+pub const INTEGRATION_USAGE: Map<(u64, &Addr), IntegrationUsage> = Map::new("integration_usage");
+// bonus rules for holders of cw721 collections
+pub const NFT_RULES: Item<Vec<NftRule>> = Item::new("nft_rules");
+// retention bonus rules per campaign
+pub const CAMPAIGN_BONUS: Map<u64, CampaignBonus> = Map::new("campaign_bonus");
+// multipliers per triggering contract and fee token of a campaign
+pub const CAMPAIGN_MULTIPLIERS: Map<u64, CampaignMultipliers> = Map::new("campaign_multipliers");
+// mapping from (campaignId, user) -> trading streak in this campaign
+pub const USER_STREAK: Map<(u64, &Addr), UserStreak> = Map::new("user_streak");
+// mapping from user -> referrer, set once by the user
+pub const REFERRER: Map<&Addr, Addr> = Map::new("referrer");
+// mapping from (referrer, referee) -> total referral reward earned from this referee
+pub const REFERRAL_EARNING: Map<(&Addr, &Addr), Uint128> = Map::new("referral_earning");
+// mapping from (referrer, campaignId) -> pending referral reward, paid in the campaign reward token
+pub const PENDING_REFERRAL_REWARD: Map<(&Addr, u64), Uint128> =
+    Map::new("pending_referral_rewards");
+
+// config changes waiting for their timelock, mapping from id -> change
+pub const PENDING_CONFIG_CHANGE: Map<u64, PendingConfigChange> = Map::new("pending_config_change");
+// last pending config change id
+pub const LAST_CONFIG_CHANGE_ID: Item<u64> = Item::new("last_config_change_id");
+// cursor of user keys still to normalize after migrate, absent when done
+pub const KEY_MIGRATION: Item<KeyMigration> = Item::new("key_migration");
+// append-only log of admin actions, mapping from index -> entry
+pub const ADMIN_LOG: Map<u64, AdminLogEntry> = Map::new("admin_log");
+
+// settings for paginated queries
+pub const DEFAULT_LIMIT: u32 = 10;
+pub const MAX_LIMIT: u32 = 30;
 
 #[cw_serde]
 pub struct Config {
-    pub owner: Addr,
+    pub owner: Option<Addr>, // none after ownership is renounced
     pub underlying_token: AssetInfo,
     pub rules: Vec<(Uint128, Decimal)>, // contain list conditions: balance - % cash back
+    pub max_cash_back_percent: Decimal, // upper bound for every rule percent
+    pub rule_mode: RuleMode,
+    pub verify_fees: bool, // callers must attach the fee tokens they report
+    pub treasury: Option<Addr>, // receiver of verified fees
+    pub referral_percent: Decimal, // share of the referee cash back credited to the referrer
+    pub timelock: u64, // delay in seconds before config changes apply, zero applies them at once
+}
+
+impl Config {
+    pub fn is_owner(&self, addr: &Addr) -> bool {
+        self.owner.as_ref() == Some(addr)
+    }
+}
+
+// validated update of config, none fields are left unchanged
+#[cw_serde]
+#[derive(Default)]
+pub struct ConfigUpdate {
+    pub underlying_token: Option<AssetInfo>,
+    pub rules: Option<Vec<(Uint128, Decimal)>>,
+    pub max_cash_back_percent: Option<Decimal>,
+    pub rule_mode: Option<RuleMode>,
+    pub verify_fees: Option<bool>,
+    pub treasury: Option<Addr>,
+    pub referral_percent: Option<Decimal>,
+    pub timelock: Option<u64>,
+}
+
+impl ConfigUpdate {
+    pub fn apply(self, config: &mut Config) {
+        if let Some(underlying_token) = self.underlying_token {
+            config.underlying_token = underlying_token;
+        }
+        if let Some(rules) = self.rules {
+            config.rules = rules;
+        }
+        if let Some(max_cash_back_percent) = self.max_cash_back_percent {
+            config.max_cash_back_percent = max_cash_back_percent;
+        }
+        if let Some(rule_mode) = self.rule_mode {
+            config.rule_mode = rule_mode;
+        }
+        if let Some(verify_fees) = self.verify_fees {
+            config.verify_fees = verify_fees;
+        }
+        if let Some(treasury) = self.treasury {
+            config.treasury = Some(treasury);
+        }
+        if let Some(referral_percent) = self.referral_percent {
+            config.referral_percent = referral_percent;
+        }
+        if let Some(timelock) = self.timelock {
+            config.timelock = timelock;
+        }
+    }
+}
+
+#[cw_serde]
+pub struct PendingConfigChange {
+    pub proposer: Addr,
+    pub execute_after: u64, // timestamp in seconds
+    pub update: ConfigUpdate,
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum Role {
+    // grant and revoke roles, perform every other role
+    Admin,
+    // campaigns, bonuses, boosts, exclusions and nft rules
+    CampaignManager,
+    // whitelisted contracts and their quotas
+    WhitelistManager,
+    // fee verification and treasury
+    Treasurer,
+    // distribute pending cash back
+    Keeper,
+    // pause and unpause the contract
+    Guardian,
+}
+
+// maps keyed by user are normalized in batches, one map after the other
+#[cw_serde]
+pub enum KeyMigration {
+    PendingCashBack { start_after: Option<Addr> },
+    UserCashBack { start_after: Option<(u64, Addr)> },
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum PausableFunction {
+    // accrual through TriggerCashBack and Receive
+    Triggers,
+    // distribution of pending cash back
+    Payouts,
+    // referral reward claims
+    Claims,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct PauseStatus {
+    pub triggers: bool,
+    pub payouts: bool,
+    pub claims: bool,
+}
+
+impl PauseStatus {
+    pub fn is_paused(&self, function: PausableFunction) -> bool {
+        match function {
+            PausableFunction::Triggers => self.triggers,
+            PausableFunction::Payouts => self.payouts,
+            PausableFunction::Claims => self.claims,
+        }
+    }
+
+    // update one function, or every function if none
+    pub fn set(&mut self, function: Option<PausableFunction>, paused: bool) {
+        match function {
+            Some(PausableFunction::Triggers) => self.triggers = paused,
+            Some(PausableFunction::Payouts) => self.payouts = paused,
+            Some(PausableFunction::Claims) => self.claims = paused,
+            None => {
+                self.triggers = paused;
+                self.payouts = paused;
+                self.claims = paused;
+            }
+        }
+    }
+}
+
+#[cw_serde]
+pub struct PendingOwner {
+    pub owner: Addr,
+    pub expires: Option<u64>, // timestamp in seconds, never expires if none
+}
+
+impl PendingOwner {
+    pub fn is_expired(&self, env: &Env) -> bool {
+        self.expires
+            .map(|expires| expires <= env.block.time.seconds())
+            .unwrap_or(false)
+    }
+}
+
+#[cw_serde]
+#[derive(Default, Copy)]
+pub enum RuleMode {
+    // percent of the highest matched threshold
+    #[default]
+    Step,
+    // percent interpolated linearly between the two surrounding thresholds
+    Linear,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum ContractKind {
+    #[default]
+    Pair,
+    Router,
+    Orderbook,
+    Aggregator,
+}
+
+#[cw_serde]
+pub struct WhitelistContractInfo {
+    pub label: String,
+    pub kind: ContractKind,
+    pub multiplier: Option<Decimal>, // applied on top of the user percent
+    pub allowed_fee_tokens: Vec<AssetInfo>, // empty means every fee token is accepted
+    pub enabled: bool,
+}
+
+impl WhitelistContractInfo {
+    pub fn is_fee_token_allowed(&self, token: &AssetInfo) -> bool {
+        self.allowed_fee_tokens.is_empty() || self.allowed_fee_tokens.contains(token)
+    }
+}
+
+#[cw_serde]
+#[derive(Copy)]
+pub enum RatePeriod {
+    Block,
+    Hour,
+}
+
+impl RatePeriod {
+    // id of the current rate limit window
+    pub fn window(&self, env: &Env) -> u64 {
+        match self {
+            RatePeriod::Block => env.block.height,
+            RatePeriod::Hour => env.block.time.seconds() / 3600,
+        }
+    }
+}
+
+#[cw_serde]
+pub struct RateLimit {
+    pub amount: Uint128, // max cash back accrued per period
+    pub period: RatePeriod,
+}
+
+#[cw_serde]
+pub struct IntegrationQuota {
+    pub budget_share: Option<Decimal>, // max share of the campaign total reward
+    pub rate_limit: Option<RateLimit>,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct IntegrationUsage {
+    pub total: Uint128,
+    pub window: u64,
+    pub window_amount: Uint128,
+}
+
+impl IntegrationUsage {
+    // accrued amount in the current window of the rate limit
+    pub fn window_amount(&self, rate_limit: &RateLimit, env: &Env) -> Uint128 {
+        if self.window == rate_limit.period.window(env) {
+            self.window_amount
+        } else {
+            Uint128::zero()
+        }
+    }
+}
+
+impl IntegrationQuota {
+    // remaining amount this integration can accrue in the campaign right now
+    pub fn remaining(&self, usage: &IntegrationUsage, total_reward: Uint128, env: &Env) -> Uint128 {
+        let mut remaining = Uint128::MAX;
+        if let Some(share) = self.budget_share {
+            remaining = remaining.min(total_reward.mul_floor(share).saturating_sub(usage.total));
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            remaining = remaining.min(
+                rate_limit
+                    .amount
+                    .saturating_sub(usage.window_amount(rate_limit, env)),
+            );
+        }
+        remaining
+    }
+}
+
+#[cw_serde]
+pub enum BoostKind {
+    // use this percent regardless of holdings
+    Fixed { percent: Decimal },
+    // multiply the percent calculated from rules
+    Multiplier { factor: Decimal },
+}
+
+#[cw_serde]
+pub struct UserBoost {
+    pub kind: BoostKind,
+    pub expires: Option<u64>, // timestamp in seconds, never expires if none
+}
+
+impl UserBoost {
+    pub fn is_expired(&self, env: &Env) -> bool {
+        self.expires
+            .map(|expires| expires <= env.block.time.seconds())
+            .unwrap_or(false)
+    }
+
+    pub fn apply(&self, percent: Decimal) -> Decimal {
+        match self.kind {
+            BoostKind::Fixed { percent } => percent,
+            BoostKind::Multiplier { factor } => percent.saturating_mul(factor),
+        }
+    }
+}
+
+#[cw_serde]
+pub enum NftBonus {
+    // holders get at least this percent
+    MinPercent { percent: Decimal },
+    // this percent is added on top of the tier percent
+    AddPercent { percent: Decimal },
+}
+
+#[cw_serde]
+pub struct NftRule {
+    pub collection: Addr,
+    pub min_tokens: u32, // user must own at least this amount of tokens of the collection
+    pub bonus: NftBonus,
+}
+
+impl NftRule {
+    pub fn apply(&self, percent: Decimal) -> Decimal {
+        match self.bonus {
+            NftBonus::MinPercent { percent: min } => percent.max(min),
+            NftBonus::AddPercent { percent: add } => percent.saturating_add(add),
+        }
+    }
 }
 
 #[cw_serde]
@@ -30,6 +377,15 @@ pub struct Campaign {
     pub total_reward: Uint128,
     pub reward_token: AssetInfo,
     pub distributed_amount: Uint128,
+    #[serde(default)]
+    pub windows: Vec<MultiplierWindow>, // happy hour windows sorted by start
+}
+
+#[cw_serde]
+pub struct MultiplierWindow {
+    pub start: u64,
+    pub end: u64,
+    pub multiplier: Decimal, // applied on top of the user percent
 }
 
 impl Campaign {
@@ -41,10 +397,391 @@ impl Campaign {
         let current = env.block.time.seconds();
         self.start <= current && self.end >= current
     }
+
+    // multiplier of the window running now, if any
+    pub fn window_multiplier(&self, env: &Env) -> Option<Decimal> {
+        let current = env.block.time.seconds();
+        self.windows
+            .iter()
+            .find(|window| window.start <= current && window.end >= current)
+            .map(|window| window.multiplier)
+    }
+
+    // apply an edit, `previous_end` is the end of the campaign before this one
+    pub fn edit(
+        &mut self,
+        env: &Env,
+        previous_end: Option<u64>,
+        start: Option<u64>,
+        end: Option<u64>,
+        total_reward: Option<Uint128>,
+    ) -> Result<(), ContractError> {
+        let current = env.block.time.seconds();
+        if let Some(start) = start.filter(|start| *start != self.start) {
+            // start can not be moved once started, nor into the past
+            if self.start <= current {
+                return Err(ContractError::CampaignAlreadyStarted {});
+            }
+            if start < current {
+                return Err(ContractError::InvalidCampaignTime {});
+            }
+            if previous_end.is_some_and(|previous_end| start <= previous_end) {
+                return Err(ContractError::CampaignOverlap {});
+            }
+            self.start = start;
+        }
+        if let Some(end) = end {
+            if end < current {
+                return Err(ContractError::InvalidCampaignTime {});
+            }
+            self.end = end;
+        }
+        if let Some(total_reward) = total_reward {
+            if total_reward < self.distributed_amount {
+                return Err(ContractError::RewardBelowDistributed {
+                    distributed: self.distributed_amount,
+                });
+            }
+            self.total_reward = total_reward;
+        }
+        if self.start > self.end {
+            return Err(ContractError::InvalidCampaignTime {});
+        }
+        // existing windows must stay inside the campaign
+        if self
+            .windows
+            .iter()
+            .any(|window| window.start < self.start || window.end > self.end)
+        {
+            return Err(ContractError::InvalidWindow {});
+        }
+        Ok(())
+    }
+}
+
+#[cw_serde]
+pub enum AdminAction {
+    UpdateConfig {},
+    WhitelistContract { contract: Addr },
+    UpdateWhitelistContract { contract: Addr },
+    RemoveContract { contract: Addr },
+    CreateCampaign { id: u64 },
+    EditCampaign { id: u64 },
+    QueueConfigChange { id: u64 },
+    // sender of the entry is the executor
+    ExecuteConfigChange { id: u64, proposer: Addr },
+    CancelConfigChange { id: u64 },
+}
+
+#[cw_serde]
+pub struct AdminLogEntry {
+    pub height: u64,
+    pub time: u64,
+    pub sender: Addr,
+    pub action: AdminAction,
+    pub diff: String, // json of {"old": .., "new": ..}, null if the value did not exist
+}
+
+#[derive(Serialize)]
+struct ValueDiff<'a, T> {
+    old: Option<&'a T>,
+    new: Option<&'a T>,
+}
+
+#[cw_serde]
+pub struct CampaignEdit {
+    pub editor: Addr,
+    pub time: u64,
+    pub start: (u64, u64), // (before, after)
+    pub end: (u64, u64),
+    pub total_reward: (Uint128, Uint128),
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct CampaignMultipliers {
+    pub contracts: Vec<(Addr, Decimal)>, // triggering contract - multiplier
+    pub tokens: Vec<(AssetInfo, Decimal)>, // fee token - multiplier, zero excludes the token
 }
+
+impl CampaignMultipliers {
+    pub fn contract_multiplier(&self, contract: &Addr) -> Decimal {
+        self.contracts
+            .iter()
+            .find(|(addr, _)| addr == contract)
+            .map(|(_, multiplier)| *multiplier)
+            .unwrap_or(Decimal::one())
+    }
+
+    pub fn token_multiplier(&self, token: &AssetInfo) -> Decimal {
+        self.tokens
+            .iter()
+            .find(|(info, _)| info == token)
+            .map(|(_, multiplier)| *multiplier)
+            .unwrap_or(Decimal::one())
+    }
+}
+
+#[cw_serde]
+pub struct StreakBonus {
+    pub days: u32, // consecutive trading days required, including today
+    pub multiplier: Decimal,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct CampaignBonus {
+    pub first_trade: Option<Uint128>, // one-off amount in reward token on the first trade of a user earning cash back
+    pub streak: Option<StreakBonus>,
+}
+
+impl CampaignBonus {
+    pub fn streak_multiplier(&self, streak: &UserStreak) -> Option<Decimal> {
+        self.streak
+            .as_ref()
+            .filter(|bonus| streak.length >= bonus.days)
+            .map(|bonus| bonus.multiplier)
+    }
+}
+
+#[cw_serde]
+#[derive(Default, Copy)]
+pub struct UserStreak {
+    pub last_day: u64, // day of the last trade, timestamp in seconds / 86400
+    pub length: u32,   // consecutive trading days up to last day
+}
+
+impl UserStreak {
+    pub fn today(env: &Env) -> u64 {
+        env.block.time.seconds() / 86400
+    }
+
+    // streak after trading on this day
+    pub fn advance(&self, day: u64) -> UserStreak {
+        let length = if self.length > 0 && self.last_day == day {
+            self.length
+        } else if self.length > 0 && self.last_day + 1 == day {
+            self.length.saturating_add(1)
+        } else {
+            1
+        };
+        UserStreak {
+            last_day: day,
+            length,
+        }
+    }
+}
+
 pub fn read_all_pending_cash_back(storage: &dyn Storage) -> Vec<(Addr, Uint128)> {
     PENDING_CASH_BACK
         .range(storage, None, None, Order::Ascending)
         .map(|item| item.unwrap())
         .collect()
 }
+
+// pending referral rewards of a referrer, or of every referrer, summed per reward token
+pub fn read_pending_referral_rewards(
+    storage: &dyn Storage,
+    referrer: Option<&Addr>,
+) -> StdResult<Vec<Asset>> {
+    let entries = match referrer {
+        Some(referrer) => PENDING_REFERRAL_REWARD
+            .prefix(referrer)
+            .range(storage, None, None, Order::Ascending)
+            .collect::<StdResult<Vec<(u64, Uint128)>>>()?,
+        None => PENDING_REFERRAL_REWARD
+            .range(storage, None, None, Order::Ascending)
+            .map(|item| item.map(|((_, id), amount)| (id, amount)))
+            .collect::<StdResult<Vec<(u64, Uint128)>>>()?,
+    };
+
+    let mut rewards: Vec<Asset> = vec![];
+    for (id, amount) in entries {
+        let info = CAMPAIGN.load(storage, id)?.reward_token;
+        match rewards.iter_mut().find(|reward| reward.info == info) {
+            Some(reward) => reward.amount = reward.amount.checked_add(amount)?,
+            None => rewards.push(Asset { info, amount }),
+        }
+    }
+    Ok(rewards)
+}
+
+pub fn read_roles(
+    storage: &dyn Storage,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Addr, Vec<Role>)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    ROLES
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+pub fn read_user_boosts(
+    storage: &dyn Storage,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Addr, UserBoost)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    USER_BOOST
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+pub fn read_excluded_users(
+    storage: &dyn Storage,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Addr, String)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    EXCLUDED_USER
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+pub fn read_whitelist_contracts(
+    storage: &dyn Storage,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Addr, WhitelistContractInfo)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    WHITELIST_CONTRACT
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+pub fn read_integration_usages(
+    storage: &dyn Storage,
+    campaign_id: u64,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Addr, IntegrationUsage)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    INTEGRATION_USAGE
+        .prefix(campaign_id)
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+// campaigns the user earned cash back in, with the earned amount
+pub fn read_user_campaigns(
+    storage: &dyn Storage,
+    user: &Addr,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(u64, Uint128)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    USER_CAMPAIGNS
+        .prefix(user)
+        .keys(storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|id| {
+            let id = id?;
+            Ok((id, USER_CASH_BACK.load(storage, (id, user))?))
+        })
+        .collect()
+}
+
+// earnings of referees of a referrer
+pub fn read_referees(
+    storage: &dyn Storage,
+    referrer: &Addr,
+    start_after: Option<Addr>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(Addr, Uint128)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    REFERRAL_EARNING
+        .prefix(referrer)
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+pub fn read_campaign_edits(
+    storage: &dyn Storage,
+    id: u64,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(u64, CampaignEdit)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    CAMPAIGN_EDITS
+        .prefix(id)
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+pub fn append_admin_log<T: Serialize>(
+    storage: &mut dyn Storage,
+    env: &Env,
+    sender: &Addr,
+    action: AdminAction,
+    old: Option<&T>,
+    new: Option<&T>,
+) -> StdResult<()> {
+    let index = ADMIN_LOG
+        .keys(storage, None, None, Order::Descending)
+        .next()
+        .transpose()?
+        .map_or(0, |last| last + 1);
+    ADMIN_LOG.save(
+        storage,
+        index,
+        &AdminLogEntry {
+            height: env.block.height,
+            time: env.block.time.seconds(),
+            sender: sender.clone(),
+            action,
+            diff: to_json_string(&ValueDiff { old, new })?,
+        },
+    )
+}
+
+pub fn read_pending_config_changes(
+    storage: &dyn Storage,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(u64, PendingConfigChange)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    PENDING_CONFIG_CHANGE
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
+
+pub fn read_admin_log(
+    storage: &dyn Storage,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<(u64, AdminLogEntry)>> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    ADMIN_LOG
+        .range(storage, start, None, Order::Ascending)
+        .take(limit)
+        .collect()
+}
@@ -18,6 +18,164 @@ macro_rules! create_entry_points_testing {
     };
 }
 
+// minimal cw721 collection: only answers `Tokens { owner }` from balances set at instantiate,
+// pages are capped like cw721-base
+pub mod mock_cw721 {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{
+        to_json_binary, Binary, Deps, DepsMut, Empty, Env, MessageInfo, Response, StdResult,
+    };
+    use cw_storage_plus::Map;
+
+    const BALANCES: Map<&str, u32> = Map::new("balances");
+    const MAX_LIMIT: u32 = 30;
+
+    #[cw_serde]
+    pub struct InstantiateMsg {
+        pub balances: Vec<(String, u32)>,
+    }
+
+    #[cw_serde]
+    pub enum QueryMsg {
+        Tokens {
+            owner: String,
+            start_after: Option<String>,
+            limit: Option<u32>,
+        },
+    }
+
+    #[cw_serde]
+    pub struct TokensResponse {
+        pub tokens: Vec<String>,
+    }
+
+    pub fn instantiate(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        msg: InstantiateMsg,
+    ) -> StdResult<Response> {
+        for (owner, balance) in msg.balances {
+            BALANCES.save(deps.storage, &owner, &balance)?;
+        }
+        Ok(Response::default())
+    }
+
+    pub fn execute(
+        _deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        _msg: Empty,
+    ) -> StdResult<Response> {
+        Ok(Response::default())
+    }
+
+    pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+        match msg {
+            QueryMsg::Tokens {
+                owner,
+                start_after,
+                limit,
+            } => {
+                let balance = BALANCES.may_load(deps.storage, &owner)?.unwrap_or_default();
+                // token ids are `{owner}-{index}`
+                let start = start_after
+                    .and_then(|id| id.rsplit('-').next()?.parse::<u32>().ok())
+                    .map_or(0, |index| index + 1);
+                let end = balance.min(start + limit.unwrap_or(10).min(MAX_LIMIT));
+                to_json_binary(&TokensResponse {
+                    tokens: (start..end).map(|id| format!("{}-{}", owner, id)).collect(),
+                })
+            }
+        }
+    }
+}
+
+// minimal pair contract: charges a fee on swap and triggers cash back with reply-on-error submessages
+pub mod mock_pair {
+    use cosmwasm_schema::cw_serde;
+    use cosmwasm_std::{
+        Addr, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdError, StdResult,
+    };
+    use cw_storage_plus::Item;
+    use oraiswap::asset::Asset;
+
+    use crate::helpers::CashBackContract;
+
+    pub const CASH_BACK_REPLY_ID: u64 = 1;
+    const CASH_BACK: Item<CashBackContract> = Item::new("cash_back");
+
+    #[cw_serde]
+    pub struct InstantiateMsg {
+        pub cash_back: Addr,
+    }
+
+    #[cw_serde]
+    pub enum ExecuteMsg {
+        Swap {
+            user: Addr,
+            fee: Asset,
+            strict: bool,
+        },
+        // trigger cash back with a plain message, without reply
+        SwapWithoutReply {
+            user: Addr,
+            fees: Vec<Asset>,
+        },
+    }
+
+    pub fn instantiate(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        msg: InstantiateMsg,
+    ) -> StdResult<Response> {
+        CASH_BACK.save(deps.storage, &CashBackContract(msg.cash_back))?;
+        Ok(Response::default())
+    }
+
+    pub fn execute(
+        deps: DepsMut,
+        _env: Env,
+        _info: MessageInfo,
+        msg: ExecuteMsg,
+    ) -> StdResult<Response> {
+        match msg {
+            ExecuteMsg::Swap { user, fee, strict } => {
+                let cash_back = CASH_BACK.load(deps.storage)?;
+                // fees are paid to the cash back contract in verify fees mode
+                let sub_msgs = if cash_back.config(&deps.querier)?.verify_fees {
+                    cash_back.paid_trigger_sub_msgs(&user, vec![fee], strict, CASH_BACK_REPLY_ID)?
+                } else {
+                    vec![cash_back.trigger_sub_msg(&user, vec![fee], strict, CASH_BACK_REPLY_ID)?]
+                };
+                Ok(Response::new()
+                    .add_attribute("action", "swap")
+                    .add_submessages(sub_msgs))
+            }
+            ExecuteMsg::SwapWithoutReply { user, fees } => {
+                let cash_back = CASH_BACK.load(deps.storage)?;
+                Ok(Response::new()
+                    .add_attribute("action", "swap")
+                    .add_message(cash_back.trigger_msg(&user, fees)?))
+            }
+        }
+    }
+
+    pub fn query(_deps: Deps, _env: Env, _msg: ExecuteMsg) -> StdResult<Binary> {
+        Err(StdError::generic_err("not supported"))
+    }
+
+    pub fn reply(_deps: DepsMut, _env: Env, msg: Reply) -> StdResult<Response> {
+        match msg.id {
+            CASH_BACK_REPLY_ID => {
+                Ok(Response::new().add_attribute("cash_back_error", msg.result.unwrap_err()))
+            }
+            _ => Err(StdError::generic_err("unknown reply id")),
+        }
+    }
+}
+
 pub struct MockApp {
     pub app: App,
     token_map: HashMap<String, Addr>, // map token name to address
@@ -70,7 +228,7 @@ impl MockApp {
         let contract_addr = self
             .app
             .instantiate_contract(code_id, sender, init_msg, send_funds, label, None)
-            .map_err(|err| err.to_string())?;
+            .map_err(|err| err.root_cause().to_string())?;
         self.app.update_block(next_block);
         Ok(contract_addr)
     }
@@ -85,7 +243,7 @@ impl MockApp {
         let response = self
             .app
             .execute_contract(sender, contract_addr, msg, send_funds)
-            .map_err(|err| err.to_string())?;
+            .map_err(|err| err.root_cause().to_string())?;
 
         self.app.update_block(next_block);
 
@@ -100,7 +258,7 @@ impl MockApp {
         self.app.wrap().query_wasm_smart(contract_addr, msg)
     }
 
-    pub fn as_querier(&self) -> QuerierWrapper {
+    pub fn as_querier(&self) -> QuerierWrapper<'_> {
         self.app.wrap()
     }
 
@@ -138,18 +296,94 @@ impl MockApp {
         underlying_token: AssetInfo,
         rules: Option<Vec<(Uint128, Decimal)>>,
     ) -> Result<Addr, String> {
-        let code_id = self.upload(Box::new(create_entry_points_testing!(crate)));
+        let code_id = self.upload(Box::new(
+            create_entry_points_testing!(crate).with_migrate(crate::contract::migrate),
+        ));
+        let contract_addr = self
+            .app
+            .instantiate_contract(
+                code_id,
+                Addr::unchecked(owner),
+                &msg::InstantiateMsg {
+                    owner: owner.to_string(),
+                    underlying_token,
+                    rules,
+                    max_cash_back_percent: None,
+                    rule_mode: None,
+                    verify_fees: None,
+                    treasury: None,
+                    referral_percent: None,
+                    timelock: None,
+                },
+                &[],
+                "cash-back-contract",
+                Some(owner.to_string()),
+            )
+            .map_err(|err| err.root_cause().to_string())?;
+        self.app.update_block(next_block);
+        Ok(contract_addr)
+    }
+
+    // migrate a contract to its current code
+    pub fn migrate<T: Serialize>(
+        &mut self,
+        sender: Addr,
+        contract_addr: Addr,
+        msg: &T,
+    ) -> Result<AppResponse, String> {
+        let code_id = self
+            .app
+            .contract_data(&contract_addr)
+            .map_err(|err| err.to_string())?
+            .code_id;
+        let response = self
+            .app
+            .migrate_contract(sender, contract_addr, msg, code_id)
+            .map_err(|err| err.root_cause().to_string())?;
+        self.app.update_block(next_block);
+        Ok(response)
+    }
+
+    pub fn create_nft_collection(&mut self, owner: &str, balances: &[(&str, u32)]) -> Addr {
+        let code_id = self.upload(Box::new(cw_multi_test::ContractWrapper::new(
+            mock_cw721::execute,
+            mock_cw721::instantiate,
+            mock_cw721::query,
+        )));
+        self.instantiate(
+            code_id,
+            Addr::unchecked(owner),
+            &mock_cw721::InstantiateMsg {
+                balances: balances
+                    .iter()
+                    .map(|(owner, balance)| (owner.to_string(), *balance))
+                    .collect(),
+            },
+            &[],
+            "cw721",
+        )
+        .unwrap()
+    }
+
+    pub fn create_pair(&mut self, owner: &str, cash_back: &Addr) -> Addr {
+        let code_id = self.upload(Box::new(
+            cw_multi_test::ContractWrapper::new(
+                mock_pair::execute,
+                mock_pair::instantiate,
+                mock_pair::query,
+            )
+            .with_reply(mock_pair::reply),
+        ));
         self.instantiate(
             code_id,
             Addr::unchecked(owner),
-            &msg::InstantiateMsg {
-                owner: Addr::unchecked(owner),
-                underlying_token,
-                rules,
+            &mock_pair::InstantiateMsg {
+                cash_back: cash_back.clone(),
             },
             &[],
-            "cash-back-contract",
+            "pair",
         )
+        .unwrap()
     }
 
     pub fn set_balances_from(
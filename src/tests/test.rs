@@ -1,14 +1,30 @@
-use std::str::FromStr;
+use crate::helpers::CashBackContract;
+use std::{cmp::Reverse, str::FromStr};
 
-use cosmwasm_std::{Addr, Decimal, Uint128};
+use cosmwasm_std::{coin, from_json, to_json_binary, Addr, Decimal, Event, StdResult, Uint128};
+use cw20::{BalanceResponse, Cw20ExecuteMsg, Cw20QueryMsg, Cw20ReceiveMsg};
+use cw_multi_test::AppResponse;
+use cw_storage_plus::{Item, Map};
 use oraiswap::asset::{Asset, AssetInfo};
 
 use crate::{
-    msg::{ExecuteMsg, QueryMsg},
-    state::{Campaign, Config},
+    msg::{
+        AdminLogInfo, AssetSolvency, CampaignEditInfo, CampaignMultipliersInfo, Cw20HookMsg,
+        ExcludedUserInfo, ExecuteMsg, IntegrationUsageResponse, MigrateMsg, NftRuleInfo,
+        OwnershipResponse, PendingConfigChangeInfo, QueryMsg, RefereeInfo, RoleInfo,
+        SimulateCashBackResponse, UserBonusesResponse, UserBoostInfo, UserCashBackResponse,
+        WhitelistContractResponse,
+    },
+    state::{
+        AdminAction, BoostKind, Campaign, CampaignBonus, CampaignEdit, CampaignMultipliers, Config,
+        ConfigUpdate, ContractKind, IntegrationQuota, KeyMigration, MultiplierWindow, NftBonus,
+        NftRule, PausableFunction, PauseStatus, PendingConfigChange, RateLimit, RatePeriod, Role,
+        RuleMode, StreakBonus, UserBoost, WhitelistContractInfo, CAMPAIGN, CONFIG, USER_CASH_BACK,
+    },
+    ContractError,
 };
 
-use super::hepler::MockApp;
+use super::hepler::{mock_pair, MockApp};
 
 #[test]
 fn test_instantiate() {
@@ -32,9 +48,15 @@ fn test_instantiate() {
     assert_eq!(
         config,
         Config {
-            owner: Addr::unchecked(owner),
+            owner: Some(Addr::unchecked(owner)),
             underlying_token,
-            rules: vec![]
+            rules: vec![],
+            max_cash_back_percent: Decimal::percent(50),
+            rule_mode: RuleMode::Step,
+            verify_fees: false,
+            treasury: None,
+            referral_percent: Decimal::zero(),
+            timelock: 0,
         }
     )
 }
@@ -62,15 +84,24 @@ fn test_update_config() {
         (Uint128::from(400u128), Decimal::from_str("0.4").unwrap()),
         (Uint128::from(300u128), Decimal::from_str("0.3").unwrap()),
     ];
-    let new_owner = Addr::unchecked("new_owner");
     let msg = ExecuteMsg::UpdateConfig {
-        owner: Some(new_owner.clone()),
         underlying_token: Some(new_token.clone()),
         rules: Some(new_rules.clone()),
+        max_cash_back_percent: None,
+        rule_mode: None,
+        verify_fees: None,
+        treasury: None,
+        referral_percent: None,
+        timelock: None,
     };
 
     // update failed, unauthorized
-    let err = mock_app.execute(new_owner.clone(), cash_back_addr.clone(), &msg, &[]);
+    let err = mock_app.execute(
+        Addr::unchecked("addr000"),
+        cash_back_addr.clone(),
+        &msg,
+        &[],
+    );
     assert!(err.is_err());
 
     // update successful
@@ -82,17 +113,429 @@ fn test_update_config() {
         .query(cash_back_addr, &QueryMsg::Config {})
         .unwrap();
 
-    new_rules.sort_by(|a, b| b.0.cmp(&a.0));
+    new_rules.sort_by_key(|rule| Reverse(rule.0));
     assert_eq!(
         config,
         Config {
-            owner: new_owner,
+            owner: Some(Addr::unchecked(owner)),
             underlying_token: new_token,
             rules: new_rules,
+            max_cash_back_percent: Decimal::percent(50),
+            rule_mode: RuleMode::Step,
+            verify_fees: false,
+            treasury: None,
+            referral_percent: Decimal::zero(),
+            timelock: 0,
         }
     )
 }
 
+#[test]
+fn test_timelock() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token,
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), None)
+        .unwrap();
+
+    let update_config =
+        |rules: Option<Vec<(Uint128, Decimal)>>, timelock: Option<u64>| ExecuteMsg::UpdateConfig {
+            underlying_token: None,
+            rules,
+            max_cash_back_percent: None,
+            rule_mode: None,
+            verify_fees: None,
+            treasury: None,
+            referral_percent: None,
+            timelock,
+        };
+
+    // delay must be between the minimum and the maximum
+    for timelock in [3600, 30 * 86400 + 1, u64::MAX] {
+        let err = mock_app
+            .execute(
+                Addr::unchecked(owner),
+                cash_back_addr.clone(),
+                &update_config(None, Some(timelock)),
+                &[],
+            )
+            .unwrap_err();
+        assert!(err.contains(
+            &ContractError::InvalidTimelock {
+                min: 86400,
+                max: 30 * 86400
+            }
+            .to_string()
+        ));
+    }
+
+    // enabling the timelock applies at once
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &update_config(None, Some(86400)),
+            &[],
+        )
+        .unwrap();
+
+    // rule change is queued
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+    let queued_at = mock_app.app.block_info().time.seconds();
+    let res = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &update_config(Some(rules.clone()), None),
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm")
+            .add_attribute("action", "queue_config_change")
+            .add_attribute("id", "1")
+    ));
+    let config: Config = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(config.rules, vec![]);
+    assert_eq!(config.timelock, 86400);
+
+    let changes: Vec<PendingConfigChangeInfo> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingConfigChanges {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        changes,
+        vec![PendingConfigChangeInfo {
+            id: 1,
+            change: PendingConfigChange {
+                proposer: Addr::unchecked(owner),
+                execute_after: queued_at + 86400,
+                update: ConfigUpdate {
+                    rules: Some(rules.clone()),
+                    ..Default::default()
+                },
+            },
+        }]
+    );
+
+    // locked until the delay has passed
+    let err = mock_app
+        .execute(
+            Addr::unchecked("sender"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::ExecuteConfigChange { id: 1 },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::ConfigChangeLocked {
+            execute_after: queued_at + 86400
+        }
+        .to_string()
+    ));
+
+    // anyone executes it afterwards
+    mock_app.app.update_block(|block| {
+        block.time = block.time.plus_seconds(86400);
+        block.height += 1;
+    });
+    mock_app
+        .execute(
+            Addr::unchecked("sender"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::ExecuteConfigChange { id: 1 },
+            &[],
+        )
+        .unwrap();
+    let config: Config = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(config.rules, rules);
+    // executor is logged apart from the proposer
+    let log: Vec<AdminLogInfo> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::AdminLog {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    let entry = &log.last().unwrap().entry;
+    assert_eq!(entry.sender, Addr::unchecked("sender"));
+    assert_eq!(
+        entry.action,
+        AdminAction::ExecuteConfigChange {
+            id: 1,
+            proposer: Addr::unchecked(owner)
+        }
+    );
+    let err = mock_app
+        .execute(
+            Addr::unchecked("sender"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::ExecuteConfigChange { id: 1 },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::ConfigChangeNotFound {}.to_string()));
+
+    // queued change can be cancelled by the owner only
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &update_config(None, Some(0)),
+            &[],
+        )
+        .unwrap();
+    let err = mock_app
+        .execute(
+            Addr::unchecked("sender"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CancelConfigChange { id: 2 },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CancelConfigChange { id: 2 },
+            &[],
+        )
+        .unwrap();
+    let changes: Vec<PendingConfigChangeInfo> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingConfigChanges {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert!(changes.is_empty());
+    let config: Config = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(config.timelock, 86400);
+
+    // change of a proposer who lost the role since queuing is not applied
+    let role_msg = |grant: bool| match grant {
+        true => ExecuteMsg::GrantRole {
+            user: "admin2".to_string(),
+            role: Role::Admin,
+        },
+        false => ExecuteMsg::RevokeRole {
+            user: "admin2".to_string(),
+            role: Role::Admin,
+        },
+    };
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &role_msg(true),
+            &[],
+        )
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked("admin2"),
+            cash_back_addr.clone(),
+            &update_config(Some(vec![]), None),
+            &[],
+        )
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &role_msg(false),
+            &[],
+        )
+        .unwrap();
+    mock_app.app.update_block(|block| {
+        block.time = block.time.plus_seconds(86400);
+        block.height += 1;
+    });
+    let err = mock_app
+        .execute(
+            Addr::unchecked("sender"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::ExecuteConfigChange { id: 3 },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
+    let config: Config = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(config.rules, rules);
+
+    // an overflowing delay returns an error instead of panicking
+    CONFIG
+        .update(
+            mock_app.app.contract_storage_mut(&cash_back_addr).as_mut(),
+            |mut config| -> StdResult<Config> {
+                config.timelock = u64::MAX;
+                Ok(config)
+            },
+        )
+        .unwrap();
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &update_config(Some(rules), None),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains("Cannot Add"));
+}
+
+#[test]
+fn test_validate_cash_back_rules() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token,
+    };
+
+    // instantiate failed, percent greater than default max percent
+    let err = mock_app
+        .create_cash_back_contract(
+            owner,
+            underlying_token.clone(),
+            Some(vec![(Uint128::from(100u128), Decimal::one())]),
+        )
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::PercentExceedsMax {
+            percent: Decimal::one(),
+            max: Decimal::percent(50)
+        }
+        .to_string()
+    ));
+
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), None)
+        .unwrap();
+
+    let update_rules =
+        |rules: Vec<(Uint128, Decimal)>, max: Option<Decimal>| ExecuteMsg::UpdateConfig {
+            underlying_token: None,
+            rules: Some(rules),
+            max_cash_back_percent: max,
+            rule_mode: None,
+            verify_fees: None,
+            treasury: None,
+            referral_percent: None,
+            timelock: None,
+        };
+
+    // duplicate threshold
+    let msg = update_rules(
+        vec![
+            (Uint128::from(100u128), Decimal::percent(10)),
+            (Uint128::from(100u128), Decimal::percent(20)),
+        ],
+        None,
+    );
+    let err = mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::DuplicateThreshold {
+            threshold: Uint128::from(100u128)
+        }
+        .to_string()
+    ));
+
+    // higher threshold with lower percent
+    let msg = update_rules(
+        vec![
+            (Uint128::from(200u128), Decimal::percent(10)),
+            (Uint128::from(100u128), Decimal::percent(20)),
+        ],
+        None,
+    );
+    let err = mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::NonMonotonicRule {
+            threshold: Uint128::from(200u128)
+        }
+        .to_string()
+    ));
+
+    // max percent can not be greater than 100%
+    let msg = update_rules(vec![], Some(Decimal::percent(101)));
+    let err = mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap_err();
+    assert!(err.contains(&ContractError::InvalidMaxPercent {}.to_string()));
+
+    // max percent of 100% is allowed, a rule may give back the whole fee
+    let msg = update_rules(
+        vec![(Uint128::from(100u128), Decimal::percent(100))],
+        Some(Decimal::percent(100)),
+    );
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+
+    // raise max percent, then rules up to this value are accepted
+    let rules = vec![
+        (Uint128::from(100u128), Decimal::percent(10)),
+        (Uint128::from(200u128), Decimal::percent(80)),
+    ];
+    let msg = update_rules(rules, Some(Decimal::percent(80)));
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+
+    // lowering max percent below current rules is rejected
+    let msg = ExecuteMsg::UpdateConfig {
+        underlying_token: None,
+        rules: None,
+        max_cash_back_percent: Some(Decimal::percent(50)),
+        rule_mode: None,
+        verify_fees: None,
+        treasury: None,
+        referral_percent: None,
+        timelock: None,
+    };
+    let err = mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::PercentExceedsMax {
+            percent: Decimal::percent(80),
+            max: Decimal::percent(50)
+        }
+        .to_string()
+    ));
+}
+
 #[test]
 fn test_create_campaign() {
     let mut mock_app = MockApp::new(&[("admin", &[])]);
@@ -114,6 +557,7 @@ fn test_create_campaign() {
         end: current + 100,
         reward_token: underlying_token.clone(),
         total_reward: Uint128::from(1000000u128),
+        require_solvency: None,
     };
     // create failed, unauthorized
     let err = mock_app.execute(Addr::unchecked("sender"), cash_back_addr.clone(), &msg, &[]);
@@ -125,16 +569,18 @@ fn test_create_campaign() {
         end: current + 100,
         reward_token: underlying_token.clone(),
         total_reward: Uint128::from(1000000u128),
+        require_solvency: None,
     };
     let err = mock_app.execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[]);
     assert!(err.is_err());
 
     // create successful
     let msg = ExecuteMsg::CreateCampaign {
-        start: current + 0,
+        start: current,
         end: current + 100,
         reward_token: underlying_token.clone(),
         total_reward: Uint128::from(1000000u128),
+        require_solvency: None,
     };
     mock_app
         .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
@@ -151,11 +597,12 @@ fn test_create_campaign() {
         last_round,
         Campaign {
             id: 1,
-            start: current + 0,
+            start: current,
             end: current + 100,
             reward_token: underlying_token.clone(),
             total_reward: Uint128::from(1000000u128),
-            distributed_amount: Uint128::zero()
+            distributed_amount: Uint128::zero(),
+            windows: vec![],
         }
     );
 
@@ -165,6 +612,7 @@ fn test_create_campaign() {
         end: current + 300,
         reward_token: underlying_token.clone(),
         total_reward: Uint128::from(1000000u128),
+        require_solvency: None,
     };
     let err = mock_app.execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[]);
     assert!(err.is_err());
@@ -182,6 +630,7 @@ fn test_create_campaign() {
         end: current + 300,
         reward_token: underlying_token.clone(),
         total_reward: Uint128::from(1000000u128),
+        require_solvency: None,
     };
     mock_app
         .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
@@ -193,6 +642,7 @@ fn test_create_campaign() {
         start: Some(current),
         end: Some(current + 200),
         total_reward: Some(Uint128::from(2000000u128)),
+        require_solvency: None,
     };
     let err = mock_app.execute(Addr::unchecked("sender"), cash_back_addr.clone(), &msg, &[]);
     assert!(err.is_err());
@@ -212,7 +662,8 @@ fn test_create_campaign() {
             end: current + 200,
             reward_token: underlying_token.clone(),
             total_reward: Uint128::from(2000000u128),
-            distributed_amount: Uint128::zero()
+            distributed_amount: Uint128::zero(),
+            windows: vec![],
         }
     );
 
@@ -225,7 +676,7 @@ fn test_create_campaign() {
     assert!(err.is_err())
 }
 #[test]
-fn test_whitelist_contract() {
+fn test_edit_campaign() {
     let mut mock_app = MockApp::new(&[("admin", &[])]);
     let owner = "admin";
     let token = "oraix";
@@ -238,66 +689,351 @@ fn test_whitelist_contract() {
         .create_cash_back_contract(owner, underlying_token.clone(), None)
         .unwrap();
 
-    let msg = ExecuteMsg::WhitelistContract {
-        contract: Addr::unchecked("contract001"),
+    let current = mock_app.app.block_info().time.seconds();
+    let msg = ExecuteMsg::CreateCampaign {
+        start: current,
+        end: current + 100,
+        reward_token: underlying_token.clone(),
+        total_reward: Uint128::from(1000u128),
+        require_solvency: None,
     };
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
 
-    // register failed, unauthorized
-    let err = mock_app.execute(Addr::unchecked("sender"), cash_back_addr.clone(), &msg, &[]);
-    assert!(err.is_err());
+    // start of a running campaign can not be moved
+    let msg = ExecuteMsg::EditCampaign {
+        id: 1,
+        start: Some(current + 10),
+        end: None,
+        total_reward: None,
+        require_solvency: None,
+    };
+    let err = mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap_err();
+    assert!(err.contains(&ContractError::CampaignAlreadyStarted {}.to_string()));
 
-    // register successful
+    // end can not be earlier than now
+    mock_app.app.update_block(|block| {
+        block.time = block.time.plus_seconds(50);
+        block.height += 1;
+    });
+    let msg = ExecuteMsg::EditCampaign {
+        id: 1,
+        start: None,
+        end: Some(current + 40),
+        total_reward: None,
+        require_solvency: None,
+    };
+    let err = mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap_err();
+    assert!(err.contains(&ContractError::InvalidCampaignTime {}.to_string()));
+
+    // unchanged start is accepted
+    let first_edit = mock_app.app.block_info().time.seconds();
+    let msg = ExecuteMsg::EditCampaign {
+        id: 1,
+        start: Some(current),
+        end: Some(current + 150),
+        total_reward: Some(Uint128::from(500u128)),
+        require_solvency: None,
+    };
     mock_app
         .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
         .unwrap();
 
-    // register other contract
-    let msg = ExecuteMsg::WhitelistContract {
-        contract: Addr::unchecked("contract002"),
+    // next campaign can not be moved before the previous one ends
+    mock_app.app.update_block(|block| {
+        block.time = block.time.plus_seconds(200);
+        block.height += 1;
+    });
+    let now = mock_app.app.block_info().time.seconds();
+    let msg = ExecuteMsg::CreateCampaign {
+        start: now + 100,
+        end: now + 200,
+        reward_token: underlying_token.clone(),
+        total_reward: Uint128::from(1000u128),
+        require_solvency: None,
     };
     mock_app
         .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
         .unwrap();
+    let msg = ExecuteMsg::EditCampaign {
+        id: 2,
+        start: Some(current + 150),
+        end: None,
+        total_reward: None,
+        require_solvency: None,
+    };
+    let err = mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap_err();
+    assert!(err.contains(&ContractError::InvalidCampaignTime {}.to_string()));
 
-    let whitelist_contract: Vec<String> = mock_app
-        .query(cash_back_addr.clone(), &QueryMsg::WhitelistContract {})
-        .unwrap();
-    assert_eq!(whitelist_contract, vec!["contract001", "contract002"]);
+    // an overlapping start is already in the past here, move campaign 1 end in storage
+    // to check the overlap rule on its own
+    {
+        let mut storage = mock_app.app.contract_storage_mut(&cash_back_addr);
+        let mut campaign = CAMPAIGN.load(storage.as_ref(), 1).unwrap();
+        campaign.end = now + 10;
+        CAMPAIGN.save(storage.as_mut(), 1, &campaign).unwrap();
+    }
+    let msg = ExecuteMsg::EditCampaign {
+        id: 2,
+        start: Some(now + 5),
+        end: None,
+        total_reward: None,
+        require_solvency: None,
+    };
+    let err = mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap_err();
+    assert!(err.contains(&ContractError::CampaignOverlap {}.to_string()));
 
-    // try remove failed, unauthorized
-    let msg = ExecuteMsg::RemoveContract {
-        contract: Addr::unchecked("contract001"),
+    // campaign not started yet can move its start
+    let second_edit = mock_app.app.block_info().time.seconds();
+    let msg = ExecuteMsg::EditCampaign {
+        id: 2,
+        start: Some(now + 50),
+        end: None,
+        total_reward: None,
+        require_solvency: None,
     };
-    let err = mock_app.execute(Addr::unchecked("sender"), cash_back_addr.clone(), &msg, &[]);
-    assert!(err.is_err());
-    // remove successful
     mock_app
         .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
         .unwrap();
-    // remove failed, contract not registered yet
-    let msg = ExecuteMsg::RemoveContract {
-        contract: Addr::unchecked("contract001"),
-    };
-    let err = mock_app.execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[]);
-    assert!(err.is_err());
 
-    let whitelist_contract: Vec<String> = mock_app
-        .query(cash_back_addr.clone(), &QueryMsg::WhitelistContract {})
+    // edits are logged per campaign
+    let edits: Vec<CampaignEditInfo> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::CampaignEdits {
+                id: 1,
+                start_after: None,
+                limit: None,
+            },
+        )
         .unwrap();
-    assert_eq!(whitelist_contract, vec!["contract002"]);
-}
-
-#[test]
-fn test_trigger_cash_back() {
+    assert_eq!(
+        edits,
+        vec![CampaignEditInfo {
+            index: 0,
+            edit: CampaignEdit {
+                editor: Addr::unchecked(owner),
+                time: first_edit,
+                start: (current, current),
+                end: (current + 100, current + 150),
+                total_reward: (Uint128::from(1000u128), Uint128::from(500u128)),
+            }
+        }]
+    );
+    let edits: Vec<CampaignEditInfo> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::CampaignEdits {
+                id: 2,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        edits,
+        vec![CampaignEditInfo {
+            index: 0,
+            edit: CampaignEdit {
+                editor: Addr::unchecked(owner),
+                time: second_edit,
+                start: (now + 100, now + 50),
+                end: (now + 200, now + 200),
+                total_reward: (Uint128::from(1000u128), Uint128::from(1000u128)),
+            }
+        }]
+    );
+    let edits: Vec<CampaignEditInfo> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::CampaignEdits {
+                id: 2,
+                start_after: Some(0),
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert!(edits.is_empty());
+}
+
+#[test]
+fn test_admin_log() {
     let mut mock_app = MockApp::new(&[("admin", &[])]);
     let owner = "admin";
     let token = "oraix";
-    let rules = vec![
-        (Uint128::from(100u128), Decimal::from_str("0.1").unwrap()),
-        (Uint128::from(200u128), Decimal::from_str("0.2").unwrap()),
-        (Uint128::from(400u128), Decimal::from_str("0.4").unwrap()),
-        (Uint128::from(300u128), Decimal::from_str("0.3").unwrap()),
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token,
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), None)
+        .unwrap();
+
+    let block = mock_app.app.block_info();
+    let current = block.time.seconds();
+    let msgs = [
+        ExecuteMsg::UpdateConfig {
+            underlying_token: None,
+            rules: None,
+            max_cash_back_percent: Some(Decimal::percent(40)),
+            rule_mode: None,
+            verify_fees: None,
+            treasury: None,
+            referral_percent: None,
+            timelock: None,
+        },
+        ExecuteMsg::WhitelistContract {
+            contract: "contract001".to_string(),
+            label: Some("pair".to_string()),
+            kind: None,
+            multiplier: None,
+            allowed_fee_tokens: None,
+        },
+        ExecuteMsg::UpdateWhitelistContract {
+            contract: "contract001".to_string(),
+            label: None,
+            kind: None,
+            multiplier: None,
+            allowed_fee_tokens: None,
+            enabled: Some(false),
+        },
+        ExecuteMsg::RemoveContract {
+            contract: "contract001".to_string(),
+        },
+        ExecuteMsg::CreateCampaign {
+            start: current + 100,
+            end: current + 200,
+            reward_token: underlying_token.clone(),
+            total_reward: Uint128::from(1000u128),
+            require_solvency: None,
+        },
+        ExecuteMsg::EditCampaign {
+            id: 1,
+            start: None,
+            end: None,
+            total_reward: Some(Uint128::from(2000u128)),
+            require_solvency: None,
+        },
     ];
+    for msg in msgs.iter() {
+        mock_app
+            .execute(Addr::unchecked(owner), cash_back_addr.clone(), msg, &[])
+            .unwrap();
+    }
+
+    // failed actions are not logged
+    let err = mock_app.execute(
+        Addr::unchecked("sender"),
+        cash_back_addr.clone(),
+        &msgs[0],
+        &[],
+    );
+    assert!(err.is_err());
+
+    let log: Vec<AdminLogInfo> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::AdminLog {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        log.iter()
+            .map(|info| (info.index, info.entry.action.clone()))
+            .collect::<Vec<_>>(),
+        vec![
+            (0, AdminAction::UpdateConfig {}),
+            (
+                1,
+                AdminAction::WhitelistContract {
+                    contract: Addr::unchecked("contract001")
+                }
+            ),
+            (
+                2,
+                AdminAction::UpdateWhitelistContract {
+                    contract: Addr::unchecked("contract001")
+                }
+            ),
+            (
+                3,
+                AdminAction::RemoveContract {
+                    contract: Addr::unchecked("contract001")
+                }
+            ),
+            (4, AdminAction::CreateCampaign { id: 1 }),
+            (5, AdminAction::EditCampaign { id: 1 }),
+        ]
+    );
+    // one block per executed message
+    for (index, info) in log.iter().enumerate() {
+        assert_eq!(info.entry.sender, Addr::unchecked(owner));
+        assert_eq!(info.entry.height, block.height + index as u64);
+        assert_eq!(info.entry.time, current + 5 * index as u64);
+    }
+
+    // diff holds old and new values as json
+    #[cosmwasm_schema::cw_serde]
+    struct Diff<T> {
+        old: Option<T>,
+        new: Option<T>,
+    }
+    let diff: Diff<Config> = from_json(&log[0].entry.diff).unwrap();
+    assert_eq!(
+        diff.old.map(|config| config.max_cash_back_percent),
+        Some(Decimal::percent(50))
+    );
+    assert_eq!(
+        diff.new.map(|config| config.max_cash_back_percent),
+        Some(Decimal::percent(40))
+    );
+    let diff: Diff<WhitelistContractInfo> = from_json(&log[1].entry.diff).unwrap();
+    assert_eq!(diff.old, None);
+    assert_eq!(diff.new.map(|info| info.label), Some("pair".to_string()));
+    let diff: Diff<WhitelistContractInfo> = from_json(&log[3].entry.diff).unwrap();
+    assert_eq!(diff.old.map(|info| info.enabled), Some(false));
+    assert_eq!(diff.new, None);
+    let diff: Diff<Campaign> = from_json(&log[5].entry.diff).unwrap();
+    assert_eq!(
+        diff.old.map(|campaign| campaign.total_reward),
+        Some(Uint128::from(1000u128))
+    );
+    assert_eq!(
+        diff.new.map(|campaign| campaign.total_reward),
+        Some(Uint128::from(2000u128))
+    );
+
+    let log: Vec<AdminLogInfo> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::AdminLog {
+                start_after: Some(3),
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+    assert_eq!(log.len(), 1);
+    assert_eq!(log[0].index, 4);
+}
+
+#[test]
+fn test_solvency() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
 
     let oraix_token = mock_app.create_token(owner, token, 0u128);
     let underlying_token = AssetInfo::Token {
@@ -306,229 +1042,4566 @@ fn test_trigger_cash_back() {
     let cash_back_addr = mock_app
         .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
         .unwrap();
-
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
+        .unwrap();
     mock_app
         .mint_token(
             owner,
             cash_back_addr.as_str(),
             oraix_token.as_str(),
-            1000000u128,
+            1000u128,
+        )
+        .unwrap();
+    let msg = ExecuteMsg::WhitelistContract {
+        contract: "contract001".to_string(),
+        label: None,
+        kind: None,
+        multiplier: None,
+        allowed_fee_tokens: None,
+    };
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+
+    // nothing owed without campaign
+    let solvency: Vec<AssetSolvency> = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::Solvency {})
+        .unwrap();
+    assert!(solvency.is_empty());
+
+    // campaign budget above the balance is refused on demand
+    let current = mock_app.app.block_info().time.seconds();
+    let msg = ExecuteMsg::CreateCampaign {
+        start: current,
+        end: current + 100,
+        reward_token: underlying_token.clone(),
+        total_reward: Uint128::from(1500u128),
+        require_solvency: Some(true),
+    };
+    let err = mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::Insolvent {
+            asset: underlying_token.to_string(),
+            deficit: Uint128::from(500u128),
+        }
+        .to_string()
+    ));
+    let msg = ExecuteMsg::CreateCampaign {
+        start: current,
+        end: current + 100,
+        reward_token: underlying_token.clone(),
+        total_reward: Uint128::from(800u128),
+        require_solvency: Some(true),
+    };
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+    let solvency: Vec<AssetSolvency> = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::Solvency {})
+        .unwrap();
+    assert_eq!(
+        solvency,
+        vec![AssetSolvency {
+            info: underlying_token.clone(),
+            balance: Uint128::from(1000u128),
+            liabilities: Uint128::from(800u128),
+            surplus: Uint128::from(200u128),
+            deficit: Uint128::zero(),
+        }]
+    );
+
+    // edit is checked as well, and allowed to go insolvent if not required
+    let msg = |require_solvency: Option<bool>| ExecuteMsg::EditCampaign {
+        id: 1,
+        start: None,
+        end: None,
+        total_reward: Some(Uint128::from(1200u128)),
+        require_solvency,
+    };
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &msg(Some(true)),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::Insolvent {
+            asset: underlying_token.to_string(),
+            deficit: Uint128::from(200u128),
+        }
+        .to_string()
+    ));
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &msg(None),
+            &[],
+        )
+        .unwrap();
+    let solvency: Vec<AssetSolvency> = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::Solvency {})
+        .unwrap();
+    assert_eq!(solvency[0].liabilities, Uint128::from(1200u128));
+    assert_eq!(solvency[0].surplus, Uint128::zero());
+    assert_eq!(solvency[0].deficit, Uint128::from(200u128));
+
+    // accrued cash back stays owed after the campaign ends
+    let msg = ExecuteMsg::TriggerCashBack {
+        user: "addr000".to_string(),
+        tokens: vec![Asset {
+            info: underlying_token.clone(),
+            amount: Uint128::from(1000u128),
+        }],
+        strict: Some(true),
+    };
+    mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &msg,
+            &[],
         )
         .unwrap();
+    mock_app.app.update_block(|block| {
+        block.time = block.time.plus_seconds(200);
+        block.height += 1;
+    });
+    let solvency: Vec<AssetSolvency> = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::Solvency {})
+        .unwrap();
+    assert_eq!(
+        solvency,
+        vec![AssetSolvency {
+            info: underlying_token.clone(),
+            balance: Uint128::from(1000u128),
+            liabilities: Uint128::from(100u128),
+            surplus: Uint128::from(900u128),
+            deficit: Uint128::zero(),
+        }]
+    );
+}
+
+#[test]
+fn test_user_history() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
 
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
+        .unwrap();
+    mock_app
+        .mint_token(
+            owner,
+            cash_back_addr.as_str(),
+            oraix_token.as_str(),
+            10000u128,
+        )
+        .unwrap();
     let msg = ExecuteMsg::WhitelistContract {
-        contract: Addr::unchecked("contract001"),
+        contract: "contract001".to_string(),
+        label: None,
+        kind: None,
+        multiplier: None,
+        allowed_fee_tokens: None,
     };
     mock_app
         .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
         .unwrap();
 
+    let create_campaign = |mock_app: &mut MockApp, reward_token: &AssetInfo| {
+        let current = mock_app.app.block_info().time.seconds();
+        let msg = ExecuteMsg::CreateCampaign {
+            start: current,
+            end: current + 100,
+            reward_token: reward_token.clone(),
+            total_reward: Uint128::from(1000u128),
+            require_solvency: None,
+        };
+        mock_app
+            .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+            .unwrap();
+    };
+    let trigger = |mock_app: &mut MockApp, fee: u128| {
+        let msg = ExecuteMsg::TriggerCashBack {
+            user: "addr000".to_string(),
+            tokens: vec![Asset {
+                info: underlying_token.clone(),
+                amount: Uint128::from(fee),
+            }],
+            strict: Some(true),
+        };
+        mock_app
+            .execute(
+                Addr::unchecked("contract001"),
+                cash_back_addr.clone(),
+                &msg,
+                &[],
+            )
+            .unwrap();
+    };
+    let finish_campaign = |mock_app: &mut MockApp| {
+        mock_app.app.update_block(|block| {
+            block.time = block.time.plus_seconds(200);
+            block.height += 1;
+        });
+    };
+
+    // campaign 1 earns 100, paid out by a distribution
+    create_campaign(&mut mock_app, &underlying_token);
+    trigger(&mut mock_app, 1000);
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CashBack {},
+            &[],
+        )
+        .unwrap();
+    finish_campaign(&mut mock_app);
+    // campaign 2 has no trade
+    create_campaign(&mut mock_app, &underlying_token);
+    finish_campaign(&mut mock_app);
+    // campaign 3 earns 50, then 30 more before and after a distribution
+    create_campaign(&mut mock_app, &underlying_token);
+    trigger(&mut mock_app, 500);
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CashBack {},
+            &[],
+        )
+        .unwrap();
+    trigger(&mut mock_app, 300);
+    // campaign 4 pays in another token and earns 20
+    finish_campaign(&mut mock_app);
     let orai = AssetInfo::NativeToken {
         denom: "orai".to_string(),
     };
-    let usdt = mock_app.create_token(owner, token, 0u128);
-    let usdt_info = AssetInfo::Token {
-        contract_addr: usdt,
+    create_campaign(&mut mock_app, &orai);
+    trigger(&mut mock_app, 200);
+
+    // each campaign reports its own reward token
+    let reward_token = |campaign_id: u64| match campaign_id {
+        4 => orai.clone(),
+        _ => underlying_token.clone(),
+    };
+    let user_cash_back = |campaign_id: u64, earned: u128, pending: u128| UserCashBackResponse {
+        campaign_id,
+        reward_token: reward_token(campaign_id),
+        earned: Uint128::from(earned),
+        claimed: Uint128::from(earned - pending),
+        pending: Uint128::from(pending),
+    };
+    let res: UserCashBackResponse = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::UserCashBack {
+                user: "addr000".to_string(),
+                campaign_id: 1,
+            },
+        )
+        .unwrap();
+    assert_eq!(res, user_cash_back(1, 100, 0));
+    let res: UserCashBackResponse = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::UserCashBack {
+                user: "addr000".to_string(),
+                campaign_id: 2,
+            },
+        )
+        .unwrap();
+    assert_eq!(res, user_cash_back(2, 0, 0));
+    let res: UserCashBackResponse = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::UserCashBack {
+                user: "addr000".to_string(),
+                campaign_id: 3,
+            },
+        )
+        .unwrap();
+    assert_eq!(res, user_cash_back(3, 80, 30));
+    let res: UserCashBackResponse = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::UserCashBack {
+                user: "addr000".to_string(),
+                campaign_id: 4,
+            },
+        )
+        .unwrap();
+    assert_eq!(res, user_cash_back(4, 20, 20));
+
+    // history skips campaigns without earnings
+    let history: Vec<UserCashBackResponse> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::UserHistory {
+                user: "addr000".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        history,
+        vec![
+            user_cash_back(1, 100, 0),
+            user_cash_back(3, 80, 30),
+            user_cash_back(4, 20, 20)
+        ]
+    );
+    let history: Vec<UserCashBackResponse> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::UserHistory {
+                user: "addr000".to_string(),
+                start_after: None,
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+    assert_eq!(history, vec![user_cash_back(1, 100, 0)]);
+    let history: Vec<UserCashBackResponse> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::UserHistory {
+                user: "addr000".to_string(),
+                start_after: Some(1),
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        history,
+        vec![user_cash_back(3, 80, 30), user_cash_back(4, 20, 20)]
+    );
+
+    // unknown campaign
+    let err = mock_app
+        .query::<UserCashBackResponse, _>(
+            cash_back_addr.clone(),
+            &QueryMsg::UserCashBack {
+                user: "addr000".to_string(),
+                campaign_id: 5,
+            },
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("not found"));
+}
+
+#[test]
+fn test_whitelist_contract() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token,
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), None)
+        .unwrap();
+
+    let msg = ExecuteMsg::WhitelistContract {
+        contract: "contract001".to_string(),
+        label: None,
+        kind: None,
+        multiplier: None,
+        allowed_fee_tokens: None,
+    };
+
+    // register failed, unauthorized
+    let err = mock_app.execute(Addr::unchecked("sender"), cash_back_addr.clone(), &msg, &[]);
+    assert!(err.is_err());
+
+    // register successful
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+
+    // register failed, already registered
+    let err = mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap_err();
+    assert!(err.contains(&ContractError::AlreadyWhitelisted {}.to_string()));
+
+    // register other contract
+    let msg = ExecuteMsg::WhitelistContract {
+        contract: "contract002".to_string(),
+        label: Some("oraix/usdt router".to_string()),
+        kind: Some(ContractKind::Router),
+        multiplier: Some(Decimal::percent(150)),
+        allowed_fee_tokens: None,
+    };
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+
+    let whitelist_contract: Vec<WhitelistContractResponse> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::WhitelistContracts {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        whitelist_contract,
+        vec![
+            WhitelistContractResponse {
+                contract: Addr::unchecked("contract001"),
+                info: WhitelistContractInfo {
+                    label: "".to_string(),
+                    kind: ContractKind::Pair,
+                    multiplier: None,
+                    allowed_fee_tokens: vec![],
+                    enabled: true,
+                }
+            },
+            WhitelistContractResponse {
+                contract: Addr::unchecked("contract002"),
+                info: WhitelistContractInfo {
+                    label: "oraix/usdt router".to_string(),
+                    kind: ContractKind::Router,
+                    multiplier: Some(Decimal::percent(150)),
+                    allowed_fee_tokens: vec![],
+                    enabled: true,
+                }
+            }
+        ]
+    );
+
+    // try remove failed, unauthorized
+    let msg = ExecuteMsg::RemoveContract {
+        contract: "contract001".to_string(),
+    };
+    let err = mock_app.execute(Addr::unchecked("sender"), cash_back_addr.clone(), &msg, &[]);
+    assert!(err.is_err());
+    // remove successful
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+    // remove failed, contract not registered yet
+    let msg = ExecuteMsg::RemoveContract {
+        contract: "contract001".to_string(),
+    };
+    let err = mock_app.execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[]);
+    assert!(err.is_err());
+
+    let whitelist_contract: Vec<WhitelistContractResponse> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::WhitelistContracts {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(whitelist_contract.len(), 1);
+    assert_eq!(
+        whitelist_contract[0].contract,
+        Addr::unchecked("contract002")
+    );
+}
+
+#[test]
+fn test_validate_addresses() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token,
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), None)
+        .unwrap();
+
+    // not normalized and malformed addresses are refused
+    for contract in ["Contract001", "c1"] {
+        let msg = ExecuteMsg::WhitelistContract {
+            contract: contract.to_string(),
+            label: None,
+            kind: None,
+            multiplier: None,
+            allowed_fee_tokens: None,
+        };
+        let err = mock_app
+            .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+            .unwrap_err();
+        assert!(err.contains("Invalid input"));
+    }
+
+    // addresses inside rules are validated too
+    let msg = ExecuteMsg::SetNftRules {
+        rules: vec![NftRuleInfo {
+            collection: "Collection001".to_string(),
+            min_tokens: 1,
+            bonus: NftBonus::AddPercent {
+                percent: Decimal::percent(5),
+            },
+        }],
+    };
+    let err = mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap_err();
+    assert!(err.contains("Invalid input"));
+
+    // token contract addresses are validated too
+    let msg = ExecuteMsg::CreateCampaign {
+        start: mock_app.app.block_info().time.seconds(),
+        end: mock_app.app.block_info().time.seconds() + 100,
+        reward_token: AssetInfo::Token {
+            contract_addr: Addr::unchecked("OraiX"),
+        },
+        total_reward: Uint128::from(1000u128),
+        require_solvency: None,
+    };
+    let err = mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap_err();
+    assert!(err.contains("Invalid input"));
+
+    let msg = ExecuteMsg::WhitelistContract {
+        contract: "contract001".to_string(),
+        label: None,
+        kind: None,
+        multiplier: None,
+        allowed_fee_tokens: None,
+    };
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+
+    // invalid user is skipped, never reverting the caller
+    let msg = ExecuteMsg::TriggerCashBack {
+        user: "USER001".to_string(),
+        tokens: vec![],
+        strict: None,
+    };
+    // the whitelist is checked first
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract002"),
+            cash_back_addr.clone(),
+            &msg,
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm-cash_back_skipped")
+            .add_attribute("user", "USER001")
+            .add_attribute("reason", "not_whitelisted")
+    ));
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &msg,
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm-cash_back_skipped")
+            .add_attribute("user", "USER001")
+            .add_attribute("reason", "error")
+    ));
+
+    // strict mode returns the error
+    let msg = ExecuteMsg::TriggerCashBack {
+        user: "USER001".to_string(),
+        tokens: vec![],
+        strict: Some(true),
+    };
+    let err = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &msg,
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains("Invalid input"));
+
+    let err = mock_app
+        .query::<Uint128, _>(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingCashBack {
+                user: "USER001".to_string(),
+            },
+        )
+        .unwrap_err();
+    assert!(err.to_string().contains("Invalid input"));
+}
+
+#[test]
+fn test_whitelist_contract_config() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
+        .unwrap();
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 300,
+                reward_token: underlying_token.clone(),
+                total_reward: Uint128::from(1000000u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let orai = AssetInfo::NativeToken {
+        denom: "orai".to_string(),
+    };
+    let usdt = AssetInfo::NativeToken {
+        denom: "usdt".to_string(),
+    };
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::WhitelistContract {
+                contract: "contract001".to_string(),
+                label: Some("orai/usdt pair".to_string()),
+                kind: Some(ContractKind::Pair),
+                multiplier: Some(Decimal::percent(200)),
+                allowed_fee_tokens: Some(vec![orai.clone()]),
+            },
+            &[],
+        )
+        .unwrap();
+
+    let trigger_msg = ExecuteMsg::TriggerCashBack {
+        user: "addr000".to_string(),
+        tokens: vec![
+            Asset {
+                info: orai.clone(),
+                amount: Uint128::from(1000u128),
+            },
+            Asset {
+                info: usdt.clone(),
+                amount: Uint128::from(1000u128),
+            },
+        ],
+        strict: None,
+    };
+    let query_pending = |mock_app: &MockApp| -> Uint128 {
+        mock_app
+            .query(
+                cash_back_addr.clone(),
+                &QueryMsg::PendingCashBack {
+                    user: "addr000".to_string(),
+                },
+            )
+            .unwrap()
+    };
+
+    // only orai fee is accepted, percent 10% * 2 => 1000 * 20% = 200
+    mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    assert_eq!(query_pending(&mock_app), Uint128::from(200u128));
+
+    // update failed, contract not whitelisted
+    let update_msg = |contract: &str, enabled: Option<bool>| ExecuteMsg::UpdateWhitelistContract {
+        contract: contract.to_string(),
+        label: None,
+        kind: None,
+        multiplier: None,
+        allowed_fee_tokens: Some(vec![]),
+        enabled,
+    };
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &update_msg("contract002", None),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::NotWhitelisted {}.to_string()));
+
+    // disable contract, trigger is ignored
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &update_msg("contract001", Some(false)),
+            &[],
+        )
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    assert_eq!(query_pending(&mock_app), Uint128::from(200u128));
+
+    // enable again, every fee token is accepted now: 2000 * 20% = 400
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &update_msg("contract001", Some(true)),
+            &[],
+        )
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    assert_eq!(query_pending(&mock_app), Uint128::from(600u128));
+
+    let contract_info: WhitelistContractInfo = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::WhitelistContractInfo {
+                contract: "contract001".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        contract_info,
+        WhitelistContractInfo {
+            label: "orai/usdt pair".to_string(),
+            kind: ContractKind::Pair,
+            multiplier: Some(Decimal::percent(200)),
+            allowed_fee_tokens: vec![],
+            enabled: true,
+        }
+    );
+}
+
+#[test]
+fn test_trigger_cash_back() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![
+        (Uint128::from(100u128), Decimal::from_str("0.1").unwrap()),
+        (Uint128::from(200u128), Decimal::from_str("0.2").unwrap()),
+        (Uint128::from(400u128), Decimal::from_str("0.4").unwrap()),
+        (Uint128::from(300u128), Decimal::from_str("0.3").unwrap()),
+    ];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+
+    mock_app
+        .mint_token(
+            owner,
+            cash_back_addr.as_str(),
+            oraix_token.as_str(),
+            1000000u128,
+        )
+        .unwrap();
+
+    let msg = ExecuteMsg::WhitelistContract {
+        contract: "contract001".to_string(),
+        label: None,
+        kind: None,
+        multiplier: None,
+        allowed_fee_tokens: None,
+    };
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+
+    let orai = AssetInfo::NativeToken {
+        denom: "orai".to_string(),
+    };
+    let usdt = mock_app.create_token(owner, token, 0u128);
+    let usdt_info = AssetInfo::Token {
+        contract_addr: usdt,
+    };
+
+    let msg = ExecuteMsg::TriggerCashBack {
+        user: "addr000".to_string(),
+        tokens: vec![
+            Asset {
+                info: orai.clone(),
+                amount: Uint128::from(1000u128),
+            },
+            Asset {
+                info: usdt_info.clone(),
+                amount: Uint128::from(2000u128),
+            },
+        ],
+        strict: None,
+    };
+
+    // case 1:  contract not register yet
+    mock_app
+        .execute(
+            Addr::unchecked("contract002"),
+            cash_back_addr.clone(),
+            &msg,
+            &[],
+        )
+        .unwrap();
+    let pending_cash_back: Uint128 = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingCashBack {
+                user: "addr000".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(pending_cash_back, Uint128::zero());
+
+    // case 2: don;t exist campaign
+    mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &msg,
+            &[],
+        )
+        .unwrap();
+    let pending_cash_back: Uint128 = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingCashBack {
+                user: "addr000".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(pending_cash_back, Uint128::zero());
+
+    // case 3: trigger success
+    let current = mock_app.app.block_info().time.seconds();
+    let msg = ExecuteMsg::CreateCampaign {
+        start: current,
+        end: current + 300,
+        reward_token: underlying_token.clone(),
+        total_reward: Uint128::from(1000u128),
+        require_solvency: None,
+    };
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+
+    let msg = ExecuteMsg::TriggerCashBack {
+        user: "addr000".to_string(),
+        tokens: vec![
+            Asset {
+                info: orai.clone(),
+                amount: Uint128::from(1000u128),
+            },
+            Asset {
+                info: usdt_info.clone(),
+                amount: Uint128::from(2000u128),
+            },
+        ],
+        strict: None,
+    };
+
+    mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &msg,
+            &[],
+        )
+        .unwrap();
+
+    // pending still 0, because balance  = 0 => no refund
+    let pending_cash_back: Uint128 = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingCashBack {
+                user: "addr000".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(pending_cash_back, Uint128::zero());
+
+    // update balance
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 150u128)
+        .unwrap();
+
+    // try trigger cash back again, fee is tier 1 (10%) => cashBackAmount = 100 + 200 = 300
+    mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &msg,
+            &[],
+        )
+        .unwrap();
+    let pending_cash_back: Uint128 = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingCashBack {
+                user: "addr000".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(pending_cash_back, Uint128::from(300u128));
+
+    // update balance to tier 2: 20%
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &msg,
+            &[],
+        )
+        .unwrap();
+    let pending_cash_back: Uint128 = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingCashBack {
+                user: "addr000".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(pending_cash_back, Uint128::from(900u128));
+
+    // try calc cash back
+    let cash_back_msg = ExecuteMsg::CashBack {};
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &cash_back_msg,
+            &[],
+        )
+        .unwrap();
+
+    // after call this func, pending become zero
+    let pending_cash_back: Uint128 = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingCashBack {
+                user: "addr000".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(pending_cash_back, Uint128::zero());
+
+    // continue trigger cashback adn  reach limit of campaign (remaining 100 token)
+    mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &msg,
+            &[],
+        )
+        .unwrap();
+    let pending_cash_back: Uint128 = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingCashBack {
+                user: "addr000".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(pending_cash_back, Uint128::from(100u128));
+
+    // query last campaign
+    let last_round: Campaign = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::LastCampaign {})
+        .unwrap();
+    assert_eq!(
+        last_round,
+        Campaign {
+            id: 1,
+            start: current,
+            end: current + 300,
+            reward_token: underlying_token.clone(),
+            total_reward: Uint128::from(1000u128),
+            distributed_amount: Uint128::from(1000u128),
+            windows: vec![],
+        }
+    );
+}
+
+#[test]
+fn test_linear_rule_mode() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![
+        (Uint128::from(100u128), Decimal::percent(10)),
+        (Uint128::from(300u128), Decimal::percent(30)),
+    ];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::WhitelistContract {
+                contract: "contract001".to_string(),
+                label: None,
+                kind: None,
+                multiplier: None,
+                allowed_fee_tokens: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 300,
+                reward_token: underlying_token.clone(),
+                total_reward: Uint128::from(1000000u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // balance between tier 1 and tier 2
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 150u128)
+        .unwrap();
+    let tokens = vec![Asset {
+        info: AssetInfo::NativeToken {
+            denom: "orai".to_string(),
+        },
+        amount: Uint128::from(1000u128),
+    }];
+    let simulate_msg = QueryMsg::SimulateCashBack {
+        user: "addr000".to_string(),
+        tokens: tokens.clone(),
+    };
+
+    // step mode: use tier 1 (10%)
+    let res: SimulateCashBackResponse = mock_app
+        .query(cash_back_addr.clone(), &simulate_msg)
+        .unwrap();
+    assert_eq!(
+        res,
+        SimulateCashBackResponse {
+            cash_back_percent: Decimal::percent(10),
+            cash_back_amount: Uint128::from(100u128),
+        }
+    );
+
+    // switch to linear mode: 10% + (30% - 10%) * 50 / 200 = 15%
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::UpdateConfig {
+                underlying_token: None,
+                rules: None,
+                max_cash_back_percent: None,
+                rule_mode: Some(RuleMode::Linear),
+                verify_fees: None,
+                treasury: None,
+                referral_percent: None,
+                timelock: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let res: SimulateCashBackResponse = mock_app
+        .query(cash_back_addr.clone(), &simulate_msg)
+        .unwrap();
+    assert_eq!(
+        res,
+        SimulateCashBackResponse {
+            cash_back_percent: Decimal::percent(15),
+            cash_back_amount: Uint128::from(150u128),
+        }
+    );
+
+    // trigger event reports the effective percent
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::TriggerCashBack {
+                user: "addr000".to_string(),
+                tokens: tokens.clone(),
+                strict: None,
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm")
+            .add_attribute("cash_back_percent", "0.15")
+            .add_attribute("cash_back_amount", "150")
+    ));
+
+    // above highest threshold, keep highest percent
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 1000u128)
+        .unwrap();
+    let res: SimulateCashBackResponse = mock_app
+        .query(cash_back_addr.clone(), &simulate_msg)
+        .unwrap();
+    assert_eq!(res.cash_back_percent, Decimal::percent(30));
+}
+
+#[test]
+fn test_user_boost() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    mock_app
+        .mint_token(owner, "addr001", oraix_token.as_str(), 100u128)
+        .unwrap();
+
+    let current = mock_app.app.block_info().time.seconds();
+    let msg = ExecuteMsg::SetUserBoosts {
+        boosts: vec![
+            UserBoostInfo {
+                user: "addr000".to_string(),
+                boost: UserBoost {
+                    kind: BoostKind::Fixed {
+                        percent: Decimal::percent(20),
+                    },
+                    expires: Some(current + 100),
+                },
+            },
+            UserBoostInfo {
+                user: "addr001".to_string(),
+                boost: UserBoost {
+                    kind: BoostKind::Multiplier {
+                        factor: Decimal::percent(300),
+                    },
+                    expires: None,
+                },
+            },
+        ],
+    };
+
+    // set failed, unauthorized
+    let err = mock_app.execute(Addr::unchecked("sender"), cash_back_addr.clone(), &msg, &[]);
+    assert!(err.is_err());
+
+    // set failed, fixed percent greater than max percent
+    let invalid_msg = ExecuteMsg::SetUserBoosts {
+        boosts: vec![UserBoostInfo {
+            user: "addr000".to_string(),
+            boost: UserBoost {
+                kind: BoostKind::Fixed {
+                    percent: Decimal::percent(60),
+                },
+                expires: None,
+            },
+        }],
+    };
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &invalid_msg,
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::PercentExceedsMax {
+            percent: Decimal::percent(60),
+            max: Decimal::percent(50)
+        }
+        .to_string()
+    ));
+
+    // set failed, already expired
+    let invalid_msg = ExecuteMsg::SetUserBoosts {
+        boosts: vec![UserBoostInfo {
+            user: "addr000".to_string(),
+            boost: UserBoost {
+                kind: BoostKind::Fixed {
+                    percent: Decimal::percent(20),
+                },
+                expires: Some(current),
+            },
+        }],
+    };
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &invalid_msg,
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::InvalidBoostExpiry {}.to_string()));
+
+    // set successful
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+    let boosts: Vec<UserBoostInfo> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::UserBoosts {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(boosts.len(), 2);
+    let boosts: Vec<UserBoostInfo> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::UserBoosts {
+                start_after: Some("addr000".to_string()),
+                limit: Some(1),
+            },
+        )
+        .unwrap();
+    assert_eq!(boosts[0].user, Addr::unchecked("addr001"));
+
+    let simulate = |mock_app: &MockApp, user: &str| -> Decimal {
+        let res: SimulateCashBackResponse = mock_app
+            .query(
+                cash_back_addr.clone(),
+                &QueryMsg::SimulateCashBack {
+                    user: user.to_string(),
+                    tokens: vec![],
+                },
+            )
+            .unwrap();
+        res.cash_back_percent
+    };
+
+    // fixed percent regardless of holdings
+    assert_eq!(simulate(&mock_app, "addr000"), Decimal::percent(20));
+    // multiplier on top of tier percent: 10% * 3
+    assert_eq!(simulate(&mock_app, "addr001"), Decimal::percent(30));
+
+    // fixed boost expired, back to rules
+    mock_app.app.update_block(|block| {
+        block.time = block.time.plus_seconds(100);
+        block.height += 1;
+    });
+    assert_eq!(simulate(&mock_app, "addr000"), Decimal::zero());
+
+    // remove boost
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::RemoveUserBoosts {
+                users: vec!["addr001".to_string()],
+            },
+            &[],
+        )
+        .unwrap();
+    let boost: Option<UserBoost> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::UserBoost {
+                user: "addr001".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(boost, None);
+    assert_eq!(simulate(&mock_app, "addr001"), Decimal::percent(10));
+}
+
+#[test]
+fn test_exclude_users() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::WhitelistContract {
+                contract: "contract001".to_string(),
+                label: None,
+                kind: None,
+                multiplier: None,
+                allowed_fee_tokens: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 300,
+                reward_token: underlying_token.clone(),
+                total_reward: Uint128::from(1000000u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let msg = ExecuteMsg::ExcludeUsers {
+        users: vec![ExcludedUserInfo {
+            user: "addr000".to_string(),
+            reason: "market maker".to_string(),
+        }],
+    };
+    // exclude failed, unauthorized
+    let err = mock_app.execute(Addr::unchecked("sender"), cash_back_addr.clone(), &msg, &[]);
+    assert!(err.is_err());
+
+    // exclude successful
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+    let excluded: Vec<ExcludedUserInfo> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::ExcludedUsers {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        excluded,
+        vec![ExcludedUserInfo {
+            user: "addr000".to_string(),
+            reason: "market maker".to_string(),
+        }]
+    );
+
+    // trigger is skipped with reason
+    let trigger_msg = ExecuteMsg::TriggerCashBack {
+        user: "addr000".to_string(),
+        tokens: vec![Asset {
+            info: AssetInfo::NativeToken {
+                denom: "orai".to_string(),
+            },
+            amount: Uint128::from(1000u128),
+        }],
+        strict: None,
+    };
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm-cash_back_skipped")
+            .add_attribute("reason", "excluded")
+            .add_attribute("detail", "market maker")
+    ));
+    let pending_cash_back: Uint128 = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingCashBack {
+                user: "addr000".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(pending_cash_back, Uint128::zero());
+
+    // include again, cash back accrues
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::IncludeUsers {
+                users: vec!["addr000".to_string()],
+            },
+            &[],
+        )
+        .unwrap();
+    let reason: Option<String> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::ExcludedUser {
+                user: "addr000".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(reason, None);
+    mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    let pending_cash_back: Uint128 = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingCashBack {
+                user: "addr000".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(pending_cash_back, Uint128::from(100u128));
+}
+
+#[test]
+fn test_nft_rules() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    mock_app
+        .mint_token(owner, "addr001", oraix_token.as_str(), 100u128)
+        .unwrap();
+    let collection =
+        mock_app.create_nft_collection(owner, &[("addr000", 2), ("addr001", 1), ("addr002", 1)]);
+
+    let msg = ExecuteMsg::SetNftRules {
+        rules: vec![
+            NftRuleInfo {
+                collection: collection.to_string(),
+                min_tokens: 2,
+                bonus: NftBonus::MinPercent {
+                    percent: Decimal::percent(20),
+                },
+            },
+            NftRuleInfo {
+                collection: collection.to_string(),
+                min_tokens: 1,
+                bonus: NftBonus::AddPercent {
+                    percent: Decimal::percent(5),
+                },
+            },
+        ],
+    };
+
+    // set failed, unauthorized
+    let err = mock_app.execute(Addr::unchecked("sender"), cash_back_addr.clone(), &msg, &[]);
+    assert!(err.is_err());
+
+    // set failed, rule requires no token
+    let invalid_msg = ExecuteMsg::SetNftRules {
+        rules: vec![NftRuleInfo {
+            collection: collection.to_string(),
+            min_tokens: 0,
+            bonus: NftBonus::AddPercent {
+                percent: Decimal::percent(5),
+            },
+        }],
+    };
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &invalid_msg,
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::InvalidNftRule {}.to_string()));
+
+    // set successful
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+    let nft_rules: Vec<NftRule> = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::NftRules {})
+        .unwrap();
+    assert_eq!(nft_rules.len(), 2);
+
+    let simulate = |mock_app: &MockApp, user: &str| -> Decimal {
+        let res: SimulateCashBackResponse = mock_app
+            .query(
+                cash_back_addr.clone(),
+                &QueryMsg::SimulateCashBack {
+                    user: user.to_string(),
+                    tokens: vec![],
+                },
+            )
+            .unwrap();
+        res.cash_back_percent
+    };
+
+    // 2 nfts, no tier: min 20% then +5%
+    assert_eq!(simulate(&mock_app, "addr000"), Decimal::percent(25));
+    // 1 nft and tier 1: 10% + 5%
+    assert_eq!(simulate(&mock_app, "addr001"), Decimal::percent(15));
+    // 1 nft, no tier: 0% + 5%
+    assert_eq!(simulate(&mock_app, "addr002"), Decimal::percent(5));
+    // no nft, no tier
+    assert_eq!(simulate(&mock_app, "addr003"), Decimal::zero());
+
+    // rule above the page size of the collection
+    let collection = mock_app.create_nft_collection(owner, &[("addr004", 40), ("addr005", 34)]);
+    let msg = ExecuteMsg::SetNftRules {
+        rules: vec![NftRuleInfo {
+            collection: collection.to_string(),
+            min_tokens: 35,
+            bonus: NftBonus::AddPercent {
+                percent: Decimal::percent(5),
+            },
+        }],
+    };
+    mock_app
+        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .unwrap();
+    assert_eq!(simulate(&mock_app, "addr004"), Decimal::percent(5));
+    assert_eq!(simulate(&mock_app, "addr005"), Decimal::zero());
+}
+
+#[test]
+fn test_verify_fees() {
+    let mut mock_app = MockApp::new(&[("contract001", &[coin(10000u128, "orai")])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::WhitelistContract {
+                contract: "contract001".to_string(),
+                label: None,
+                kind: None,
+                multiplier: None,
+                allowed_fee_tokens: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 300,
+                reward_token: underlying_token.clone(),
+                total_reward: Uint128::from(1000000u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let usdt = mock_app.create_token(owner, "usdt", 0u128);
+    let trigger_msg = |token: AssetInfo| ExecuteMsg::TriggerCashBack {
+        user: "addr000".to_string(),
+        tokens: vec![Asset {
+            info: token,
+            amount: Uint128::from(1000u128),
+        }],
+        strict: None,
+    };
+    let orai = AssetInfo::NativeToken {
+        denom: "orai".to_string(),
+    };
+    let query_pending = |mock_app: &MockApp| -> Uint128 {
+        mock_app
+            .query(
+                cash_back_addr.clone(),
+                &QueryMsg::PendingCashBack {
+                    user: "addr000".to_string(),
+                },
+            )
+            .unwrap()
+    };
+    mock_app
+        .mint_token(owner, "contract001", usdt.as_str(), 2000u128)
+        .unwrap();
+    mock_app
+        .mint_token(owner, "contract002", usdt.as_str(), 1000u128)
+        .unwrap();
+    let send_msg = Cw20ExecuteMsg::Send {
+        contract: cash_back_addr.to_string(),
+        amount: Uint128::from(1000u128),
+        msg: to_json_binary(&Cw20HookMsg::TriggerCashBack {
+            user: "addr000".to_string(),
+            strict: None,
+        })
+        .unwrap(),
+    };
+
+    // fees can not be paid outside verify fees mode
+    let err = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg(orai.clone()),
+            &[coin(1000u128, "orai")],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::FeesNotVerified {}.to_string()));
+    let err = mock_app
+        .execute(Addr::unchecked("contract001"), usdt.clone(), &send_msg, &[])
+        .unwrap_err();
+    assert!(err.contains(&ContractError::FeesNotVerified {}.to_string()));
+
+    // verify fees mode requires a treasury
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::UpdateConfig {
+                underlying_token: None,
+                rules: None,
+                max_cash_back_percent: None,
+                rule_mode: None,
+                verify_fees: Some(true),
+                treasury: None,
+                referral_percent: None,
+                timelock: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::TreasuryRequired {}.to_string()));
+
+    // enable verify fees mode
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::UpdateConfig {
+                underlying_token: None,
+                rules: None,
+                max_cash_back_percent: None,
+                rule_mode: None,
+                verify_fees: Some(true),
+                treasury: Some("treasury".to_string()),
+                referral_percent: None,
+                timelock: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // trigger failed, fee not attached
+    let err = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg(orai.clone()),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::FeeMismatch {}.to_string()));
+
+    // trigger failed, attached amount mismatch
+    let err = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg(orai.clone()),
+            &[coin(999u128, "orai")],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::FeeMismatch {}.to_string()));
+
+    // trigger failed, cw20 fee must be sent through Receive
+    let err = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg(AssetInfo::Token {
+                contract_addr: usdt.clone(),
+            }),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::FeeMismatch {}.to_string()));
+
+    // trigger successful, fee forwarded to treasury
+    mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg(orai.clone()),
+            &[coin(1000u128, "orai")],
+        )
+        .unwrap();
+    assert_eq!(query_pending(&mock_app), Uint128::from(100u128));
+    assert_eq!(
+        mock_app
+            .as_querier()
+            .query_balance("treasury", "orai")
+            .unwrap()
+            .amount,
+        Uint128::from(1000u128)
+    );
+
+    // cw20 fee token must be listed for the calling contract
+    let err = mock_app
+        .execute(Addr::unchecked("contract001"), usdt.clone(), &send_msg, &[])
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Cw20FeeNotAllowed {}.to_string()));
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::UpdateWhitelistContract {
+                contract: "contract001".to_string(),
+                label: None,
+                kind: None,
+                multiplier: None,
+                allowed_fee_tokens: Some(vec![
+                    orai.clone(),
+                    AssetInfo::Token {
+                        contract_addr: usdt.clone(),
+                    },
+                ]),
+                enabled: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // spoofed hook sender is rejected, the caller is not a listed token
+    let spoofed_msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+        sender: "contract001".to_string(),
+        amount: Uint128::from(1000000u128),
+        msg: to_json_binary(&Cw20HookMsg::TriggerCashBack {
+            user: "attacker".to_string(),
+            strict: None,
+        })
+        .unwrap(),
+    });
+    let err = mock_app
+        .execute(
+            Addr::unchecked("attacker"),
+            cash_back_addr.clone(),
+            &spoofed_msg,
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Cw20FeeNotAllowed {}.to_string()));
+    let err = mock_app
+        .execute(Addr::unchecked("contract002"), usdt.clone(), &send_msg, &[])
+        .unwrap_err();
+    assert!(err.contains(&ContractError::NotWhitelisted {}.to_string()));
+
+    // trigger with cw20 fee through Receive
+    mock_app
+        .execute(Addr::unchecked("contract001"), usdt.clone(), &send_msg, &[])
+        .unwrap();
+    assert_eq!(query_pending(&mock_app), Uint128::from(200u128));
+    let balance: BalanceResponse = mock_app
+        .query(
+            usdt.clone(),
+            &Cw20QueryMsg::Balance {
+                address: "treasury".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(balance.balance, Uint128::from(1000u128));
+}
+
+#[test]
+fn test_integration_quota() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::WhitelistContract {
+                contract: "contract001".to_string(),
+                label: None,
+                kind: None,
+                multiplier: None,
+                allowed_fee_tokens: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 10000,
+                reward_token: underlying_token.clone(),
+                total_reward: Uint128::from(1000u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let rate_limit = RateLimit {
+        amount: Uint128::from(150u128),
+        period: RatePeriod::Hour,
+    };
+    let quota_msg = |contract: &str, budget_share: Decimal| ExecuteMsg::SetIntegrationQuota {
+        contract: contract.to_string(),
+        budget_share: Some(budget_share),
+        rate_limit: Some(rate_limit.clone()),
+    };
+
+    // set failed, unauthorized
+    let err = mock_app.execute(
+        Addr::unchecked("sender"),
+        cash_back_addr.clone(),
+        &quota_msg("contract001", Decimal::percent(30)),
+        &[],
+    );
+    assert!(err.is_err());
+
+    // set failed, contract not whitelisted
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &quota_msg("contract002", Decimal::percent(30)),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::NotWhitelisted {}.to_string()));
+
+    // set failed, share greater than 100%
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &quota_msg("contract001", Decimal::percent(101)),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::InvalidQuota {}.to_string()));
+
+    // set successful: 30% of campaign (300) and 150 per hour
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &quota_msg("contract001", Decimal::percent(30)),
+            &[],
+        )
+        .unwrap();
+
+    let trigger_msg = ExecuteMsg::TriggerCashBack {
+        user: "addr000".to_string(),
+        tokens: vec![Asset {
+            info: AssetInfo::NativeToken {
+                denom: "orai".to_string(),
+            },
+            amount: Uint128::from(1000u128),
+        }],
+        strict: None,
+    };
+    let trigger = |mock_app: &mut MockApp| -> Uint128 {
+        mock_app
+            .execute(
+                Addr::unchecked("contract001"),
+                cash_back_addr.clone(),
+                &trigger_msg,
+                &[],
+            )
+            .unwrap();
+        mock_app
+            .query(
+                cash_back_addr.clone(),
+                &QueryMsg::PendingCashBack {
+                    user: "addr000".to_string(),
+                },
+            )
+            .unwrap()
+    };
+
+    // each trigger accrues 100, capped by rate limit of the hour
+    assert_eq!(trigger(&mut mock_app), Uint128::from(100u128));
+    assert_eq!(trigger(&mut mock_app), Uint128::from(150u128));
+    assert_eq!(trigger(&mut mock_app), Uint128::from(150u128));
+
+    // next hour, capped by budget share
+    mock_app.app.update_block(|block| {
+        block.time = block.time.plus_seconds(3600);
+        block.height += 1;
+    });
+    assert_eq!(trigger(&mut mock_app), Uint128::from(250u128));
+    assert_eq!(trigger(&mut mock_app), Uint128::from(300u128));
+    mock_app.app.update_block(|block| {
+        block.time = block.time.plus_seconds(3600);
+        block.height += 1;
+    });
+    assert_eq!(trigger(&mut mock_app), Uint128::from(300u128));
+
+    let usages: Vec<IntegrationUsageResponse> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::IntegrationUsages {
+                campaign_id: None,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(usages.len(), 1);
+    assert_eq!(usages[0].contract, Addr::unchecked("contract001"));
+    assert_eq!(usages[0].campaign_id, 1);
+    assert_eq!(usages[0].usage.total, Uint128::from(300u128));
+    assert_eq!(
+        usages[0].quota,
+        Some(IntegrationQuota {
+            budget_share: Some(Decimal::percent(30)),
+            rate_limit: Some(rate_limit),
+        })
+    );
+
+    // removing the contract drops its quota, it is not restored when whitelisted again
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::RemoveContract {
+                contract: "contract001".to_string(),
+            },
+            &[],
+        )
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::WhitelistContract {
+                contract: "contract001".to_string(),
+                label: None,
+                kind: None,
+                multiplier: None,
+                allowed_fee_tokens: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let usages: Vec<IntegrationUsageResponse> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::IntegrationUsages {
+                campaign_id: None,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(usages[0].quota, None);
+}
+
+#[test]
+fn test_skip_reasons() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::WhitelistContract {
+                contract: "contract001".to_string(),
+                label: None,
+                kind: None,
+                multiplier: None,
+                allowed_fee_tokens: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let trigger_msg = |strict: bool| ExecuteMsg::TriggerCashBack {
+        user: "addr000".to_string(),
+        tokens: vec![Asset {
+            info: AssetInfo::NativeToken {
+                denom: "orai".to_string(),
+            },
+            amount: Uint128::from(1000u128),
+        }],
+        strict: Some(strict),
+    };
+    let check_skip = |mock_app: &mut MockApp, sender: &str, reason: &str, err: ContractError| {
+        let res = mock_app
+            .execute(
+                Addr::unchecked(sender),
+                cash_back_addr.clone(),
+                &trigger_msg(false),
+                &[],
+            )
+            .unwrap();
+        assert!(res.has_event(
+            &Event::new("wasm-cash_back_skipped")
+                .add_attribute("user", "addr000")
+                .add_attribute("reason", reason)
+        ));
+        let strict_err = mock_app
+            .execute(
+                Addr::unchecked(sender),
+                cash_back_addr.clone(),
+                &trigger_msg(true),
+                &[],
+            )
+            .unwrap_err();
+        assert_eq!(strict_err, err.to_string());
+    };
+
+    check_skip(
+        &mut mock_app,
+        "contract002",
+        "not_whitelisted",
+        ContractError::NotWhitelisted {},
+    );
+    check_skip(
+        &mut mock_app,
+        "contract001",
+        "no_campaign",
+        ContractError::NoCampaign {},
+    );
+
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current + 100,
+                end: current + 300,
+                reward_token: underlying_token.clone(),
+                total_reward: Uint128::from(100u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+    check_skip(
+        &mut mock_app,
+        "contract001",
+        "campaign_not_in_progress",
+        ContractError::CampaignNotInProgress {},
+    );
+
+    mock_app.app.update_block(|block| {
+        block.time = block.time.plus_seconds(100);
+        block.height += 1;
+    });
+    check_skip(
+        &mut mock_app,
+        "contract001",
+        "zero_percent",
+        ContractError::ZeroCashBackPercent {},
+    );
+
+    // use up campaign budget
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
+        .unwrap();
+
+    // fees earn nothing
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::TriggerCashBack {
+                user: "addr000".to_string(),
+                tokens: vec![],
+                strict: None,
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm-cash_back_skipped")
+            .add_attribute("user", "addr000")
+            .add_attribute("reason", "zero_amount")
+    ));
+
+    mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg(true),
+            &[],
+        )
+        .unwrap();
+    check_skip(
+        &mut mock_app,
+        "contract001",
+        "budget_exhausted",
+        ContractError::BudgetExhausted {},
+    );
+}
+
+#[test]
+fn test_cash_back_contract_helper() {
+    let mut mock_app = MockApp::new(&[("addr000", &[coin(10000u128, "orai")])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    let cash_back = CashBackContract(cash_back_addr.clone());
+    let pair = mock_app.create_pair(owner, &cash_back_addr);
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
+        .unwrap();
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 300,
+                reward_token: underlying_token.clone(),
+                total_reward: Uint128::from(1000000u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let orai = AssetInfo::NativeToken {
+        denom: "orai".to_string(),
+    };
+    let usdt = mock_app.create_token(owner, "usdt", 0u128);
+    let fee = Asset {
+        info: orai.clone(),
+        amount: Uint128::from(1000u128),
+    };
+    let user = Addr::unchecked("addr000");
+    let swap_msg = |fee: &Asset, strict: bool| mock_pair::ExecuteMsg::Swap {
+        user: user.clone(),
+        fee: fee.clone(),
+        strict,
+    };
+    let cash_back_error = |res: &AppResponse| -> Option<String> {
+        res.events.iter().find_map(|event| {
+            event
+                .attributes
+                .iter()
+                .find(|attr| attr.key == "cash_back_error")
+                .map(|attr| attr.value.clone())
+        })
+    };
+
+    // query helpers
+    let config = cash_back.config(&mock_app.as_querier()).unwrap();
+    assert_eq!(config.owner, Some(Addr::unchecked(owner)));
+    let simulate = cash_back
+        .simulate_cash_back(&mock_app.as_querier(), &user, vec![fee.clone()])
+        .unwrap();
+    assert_eq!(simulate.cash_back_amount, Uint128::from(100u128));
+
+    // pair not whitelisted, trigger is skipped and swap succeeds
+    let res = mock_app
+        .execute(user.clone(), pair.clone(), &swap_msg(&fee, false), &[])
+        .unwrap();
+    assert_eq!(cash_back_error(&res), None);
+    assert_eq!(
+        cash_back
+            .pending_cash_back(&mock_app.as_querier(), &user)
+            .unwrap(),
+        Uint128::zero()
+    );
+
+    // in strict mode, the skip is caught by the pair reply handler
+    let res = mock_app
+        .execute(user.clone(), pair.clone(), &swap_msg(&fee, true), &[])
+        .unwrap();
+    assert!(cash_back_error(&res)
+        .unwrap()
+        .contains(&ContractError::NotWhitelisted {}.to_string()));
+
+    // enable verify fees mode
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::WhitelistContract {
+                contract: pair.to_string(),
+                label: None,
+                kind: None,
+                multiplier: None,
+                allowed_fee_tokens: Some(vec![
+                    orai.clone(),
+                    AssetInfo::Token {
+                        contract_addr: usdt.clone(),
+                    },
+                ]),
+            },
+            &[],
+        )
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::UpdateConfig {
+                underlying_token: None,
+                rules: None,
+                max_cash_back_percent: None,
+                rule_mode: None,
+                verify_fees: Some(true),
+                treasury: Some("treasury".to_string()),
+                referral_percent: None,
+                timelock: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // pair can not pay the fee, trigger fails but swap still succeeds
+    let res = mock_app
+        .execute(user.clone(), pair.clone(), &swap_msg(&fee, false), &[])
+        .unwrap();
+    assert!(cash_back_error(&res).is_some());
+    assert_eq!(
+        cash_back
+            .pending_cash_back(&mock_app.as_querier(), &user)
+            .unwrap(),
+        Uint128::zero()
+    );
+
+    // native fee is attached as funds and forwarded to the treasury
+    let res = mock_app
+        .execute(
+            user.clone(),
+            pair.clone(),
+            &swap_msg(&fee, false),
+            &[coin(1000u128, "orai")],
+        )
+        .unwrap();
+    assert_eq!(cash_back_error(&res), None);
+    assert_eq!(
+        cash_back
+            .pending_cash_back(&mock_app.as_querier(), &user)
+            .unwrap(),
+        Uint128::from(100u128)
+    );
+    assert_eq!(
+        mock_app
+            .as_querier()
+            .query_balance("treasury", "orai")
+            .unwrap()
+            .amount,
+        Uint128::from(1000u128)
+    );
+
+    // cw20 fee is sent through the token contract
+    mock_app
+        .mint_token(owner, pair.as_str(), usdt.as_str(), 1000u128)
+        .unwrap();
+    let usdt_fee = Asset {
+        info: AssetInfo::Token {
+            contract_addr: usdt.clone(),
+        },
+        amount: Uint128::from(1000u128),
+    };
+    let res = mock_app
+        .execute(user.clone(), pair.clone(), &swap_msg(&usdt_fee, true), &[])
+        .unwrap();
+    assert_eq!(cash_back_error(&res), None);
+    assert_eq!(
+        cash_back
+            .pending_cash_back(&mock_app.as_querier(), &user)
+            .unwrap(),
+        Uint128::from(200u128)
+    );
+    let balance: BalanceResponse = mock_app
+        .query(
+            usdt.clone(),
+            &Cw20QueryMsg::Balance {
+                address: "treasury".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(balance.balance, Uint128::from(1000u128));
+
+    // disable verify fees mode, cash back accrues on reported fees
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::UpdateConfig {
+                underlying_token: None,
+                rules: None,
+                max_cash_back_percent: None,
+                rule_mode: None,
+                verify_fees: Some(false),
+                treasury: None,
+                referral_percent: None,
+                timelock: None,
+            },
+            &[],
+        )
+        .unwrap();
+    mock_app
+        .execute(user.clone(), pair.clone(), &swap_msg(&fee, false), &[])
+        .unwrap();
+    assert_eq!(
+        cash_back
+            .pending_cash_back(&mock_app.as_querier(), &user)
+            .unwrap(),
+        Uint128::from(300u128)
+    );
+}
+
+#[test]
+fn test_trigger_never_reverts_swap() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    let pair = mock_app.create_pair(owner, &cash_back_addr);
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::WhitelistContract {
+                contract: pair.to_string(),
+                label: None,
+                kind: None,
+                multiplier: None,
+                allowed_fee_tokens: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 300,
+                reward_token: underlying_token.clone(),
+                total_reward: Uint128::from(1000u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let user = Addr::unchecked("addr000");
+    let fee = Asset {
+        info: AssetInfo::NativeToken {
+            denom: "orai".to_string(),
+        },
+        amount: Uint128::from(1000u128),
+    };
+    let query_pending = |mock_app: &MockApp| -> Uint128 {
+        mock_app
+            .query(
+                cash_back_addr.clone(),
+                &QueryMsg::PendingCashBack {
+                    user: user.to_string(),
+                },
+            )
+            .unwrap()
+    };
+
+    // overflow while converting fees is reported, swap succeeds
+    let res = mock_app
+        .execute(
+            user.clone(),
+            pair.clone(),
+            &mock_pair::ExecuteMsg::SwapWithoutReply {
+                user: user.clone(),
+                fees: vec![
+                    Asset {
+                        info: fee.info.clone(),
+                        amount: Uint128::MAX,
+                    };
+                    11
+                ],
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm-cash_back_skipped")
+            .add_attribute("user", "addr000")
+            .add_attribute("reason", "error")
+    ));
+    assert_eq!(query_pending(&mock_app), Uint128::zero());
+
+    // accrue some cash back
+    mock_app
+        .execute(
+            user.clone(),
+            pair.clone(),
+            &mock_pair::ExecuteMsg::SwapWithoutReply {
+                user: user.clone(),
+                fees: vec![fee.clone()],
+            },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(query_pending(&mock_app), Uint128::from(100u128));
+
+    // total reward can not shrink below the distributed amount
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::EditCampaign {
+                id: 1,
+                start: None,
+                end: None,
+                total_reward: Some(Uint128::from(50u128)),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::RewardBelowDistributed {
+            distributed: Uint128::from(100u128)
+        }
+        .to_string()
+    ));
+
+    // total reward shrinks to the distributed amount, trigger is skipped
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::EditCampaign {
+                id: 1,
+                start: None,
+                end: None,
+                total_reward: Some(Uint128::from(100u128)),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let res = mock_app
+        .execute(
+            user.clone(),
+            pair.clone(),
+            &mock_pair::ExecuteMsg::SwapWithoutReply {
+                user: user.clone(),
+                fees: vec![fee.clone()],
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm-cash_back_skipped").add_attribute("reason", "budget_exhausted")
+    ));
+    assert_eq!(query_pending(&mock_app), Uint128::from(100u128));
+}
+
+#[test]
+fn test_referral() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    mock_app
+        .mint_token(owner, "alice", oraix_token.as_str(), 100u128)
+        .unwrap();
+    mock_app
+        .mint_token(
+            owner,
+            cash_back_addr.as_str(),
+            oraix_token.as_str(),
+            1000u128,
+        )
+        .unwrap();
+
+    // referral percent must be lte 1
+    let update_config = |referral_percent: Decimal| ExecuteMsg::UpdateConfig {
+        underlying_token: None,
+        rules: None,
+        max_cash_back_percent: None,
+        rule_mode: None,
+        verify_fees: None,
+        treasury: None,
+        referral_percent: Some(referral_percent),
+        timelock: None,
+    };
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &update_config(Decimal::percent(101)),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::InvalidReferralPercent {}.to_string()));
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &update_config(Decimal::percent(20)),
+            &[],
+        )
+        .unwrap();
+
+    // set referrer
+    let set_referrer = |referrer: &str| ExecuteMsg::SetReferrer {
+        referrer: referrer.to_string(),
+    };
+    mock_app
+        .execute(
+            Addr::unchecked("alice"),
+            cash_back_addr.clone(),
+            &set_referrer("bob"),
+            &[],
+        )
+        .unwrap();
+    let err = mock_app
+        .execute(
+            Addr::unchecked("alice"),
+            cash_back_addr.clone(),
+            &set_referrer("carol"),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::ReferrerAlreadySet {}.to_string()));
+
+    // self referral and cycles are rejected
+    let err = mock_app
+        .execute(
+            Addr::unchecked("carol"),
+            cash_back_addr.clone(),
+            &set_referrer("carol"),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::SelfReferral {}.to_string()));
+    let err = mock_app
+        .execute(
+            Addr::unchecked("bob"),
+            cash_back_addr.clone(),
+            &set_referrer("alice"),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::ReferralCycle {}.to_string()));
+    mock_app
+        .execute(
+            Addr::unchecked("bob"),
+            cash_back_addr.clone(),
+            &set_referrer("carol"),
+            &[],
+        )
+        .unwrap();
+    let err = mock_app
+        .execute(
+            Addr::unchecked("carol"),
+            cash_back_addr.clone(),
+            &set_referrer("alice"),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::ReferralCycle {}.to_string()));
+
+    let referrer: Option<Addr> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::Referrer {
+                user: "alice".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(referrer, Some(Addr::unchecked("bob")));
+
+    // whitelist contract and create campaign
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::WhitelistContract {
+                contract: "contract001".to_string(),
+                label: None,
+                kind: None,
+                multiplier: None,
+                allowed_fee_tokens: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 300,
+                reward_token: underlying_token.clone(),
+                total_reward: Uint128::from(115u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let trigger_msg = ExecuteMsg::TriggerCashBack {
+        user: "alice".to_string(),
+        tokens: vec![Asset {
+            info: AssetInfo::NativeToken {
+                denom: "orai".to_string(),
+            },
+            amount: Uint128::from(500u128),
+        }],
+        strict: None,
+    };
+
+    // alice gets 10% cash back, bob gets 20% of it
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm")
+            .add_attribute("cash_back_amount", "50")
+            .add_attribute("referrer", "bob")
+            .add_attribute("referral_amount", "10")
+    ));
+
+    // referral reward is capped by the remaining budget
+    mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    let campaign: Campaign = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::LastCampaign {})
+        .unwrap();
+    assert_eq!(campaign.distributed_amount, Uint128::from(115u128));
+
+    let pending: Vec<Asset> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingReferralReward {
+                referrer: "bob".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        pending,
+        vec![Asset {
+            info: underlying_token.clone(),
+            amount: Uint128::from(15u128),
+        }]
+    );
+    let referees: Vec<RefereeInfo> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::Referees {
+                referrer: "bob".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        referees,
+        vec![RefereeInfo {
+            referee: Addr::unchecked("alice"),
+            earned: Uint128::from(15u128),
+        }]
+    );
+
+    // rewards of the next campaign are kept in its own reward token
+    mock_app.app.update_block(|block| {
+        block.time = block.time.plus_seconds(300);
+    });
+    let usdt_token = mock_app.create_token(owner, "usdt", 0u128);
+    let usdt = AssetInfo::Token {
+        contract_addr: usdt_token.clone(),
+    };
+    mock_app
+        .mint_token(owner, cash_back_addr.as_str(), usdt_token.as_str(), 100u128)
+        .unwrap();
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 300,
+                reward_token: usdt.clone(),
+                total_reward: Uint128::from(100u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    // referral reward counts toward the quota of the calling contract
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::SetIntegrationQuota {
+                contract: "contract001".to_string(),
+                budget_share: Some(Decimal::percent(55)),
+                rate_limit: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm")
+            .add_attribute("cash_back_amount", "50")
+            .add_attribute("referrer", "bob")
+            .add_attribute("referral_amount", "5")
+    ));
+    let usages: Vec<IntegrationUsageResponse> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::IntegrationUsages {
+                campaign_id: None,
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(usages[0].usage.total, Uint128::from(55u128));
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm-cash_back_skipped").add_attribute("reason", "quota_exceeded")
+    ));
+
+    let pending: Vec<Asset> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingReferralReward {
+                referrer: "bob".to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        pending,
+        vec![
+            Asset {
+                info: underlying_token.clone(),
+                amount: Uint128::from(15u128),
+            },
+            Asset {
+                info: usdt.clone(),
+                amount: Uint128::from(5u128),
+            },
+        ]
+    );
+
+    // claim referral reward, paid in both reward tokens
+    mock_app
+        .execute(
+            Addr::unchecked("bob"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::ClaimReferralReward {},
+            &[],
+        )
+        .unwrap();
+    for (token, amount) in [(&oraix_token, 15u128), (&usdt_token, 5u128)] {
+        let balance: BalanceResponse = mock_app
+            .query(
+                token.clone(),
+                &Cw20QueryMsg::Balance {
+                    address: "bob".to_string(),
+                },
+            )
+            .unwrap();
+        assert_eq!(balance.balance, Uint128::from(amount));
+    }
+    let err = mock_app
+        .execute(
+            Addr::unchecked("bob"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::ClaimReferralReward {},
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::NothingToClaim {}.to_string()));
+}
+
+#[test]
+fn test_campaign_bonus() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::WhitelistContract {
+                contract: "contract001".to_string(),
+                label: None,
+                kind: None,
+                multiplier: None,
+                allowed_fee_tokens: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 10 * 86400,
+                reward_token: underlying_token.clone(),
+                total_reward: Uint128::from(1000u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let bonus = CampaignBonus {
+        first_trade: Some(Uint128::from(5u128)),
+        streak: Some(StreakBonus {
+            days: 3,
+            multiplier: Decimal::percent(200),
+        }),
+    };
+
+    // set bonus failed, unauthorized
+    let err = mock_app
+        .execute(
+            Addr::unchecked("addr000"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::SetCampaignBonus {
+                id: 1,
+                bonus: Some(bonus.clone()),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
+
+    // set bonus failed, streak must require at least one day
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::SetCampaignBonus {
+                id: 1,
+                bonus: Some(CampaignBonus {
+                    first_trade: None,
+                    streak: Some(StreakBonus {
+                        days: 0,
+                        multiplier: Decimal::percent(200),
+                    }),
+                }),
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::InvalidStreakBonus {}.to_string()));
+
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::SetCampaignBonus {
+                id: 1,
+                bonus: Some(bonus.clone()),
+            },
+            &[],
+        )
+        .unwrap();
+    let res: Option<CampaignBonus> = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::CampaignBonus { id: 1 })
+        .unwrap();
+    assert_eq!(res, Some(bonus));
+
+    let user = Addr::unchecked("addr000");
+    let trigger = |mock_app: &mut MockApp| {
+        mock_app
+            .execute(
+                Addr::unchecked("contract001"),
+                cash_back_addr.clone(),
+                &ExecuteMsg::TriggerCashBack {
+                    user: user.to_string(),
+                    tokens: vec![Asset {
+                        info: AssetInfo::NativeToken {
+                            denom: "orai".to_string(),
+                        },
+                        amount: Uint128::from(100u128),
+                    }],
+                    strict: None,
+                },
+                &[],
+            )
+            .unwrap()
+    };
+    let query_bonuses = |mock_app: &MockApp| -> UserBonusesResponse {
+        mock_app
+            .query(
+                cash_back_addr.clone(),
+                &QueryMsg::UserBonuses {
+                    user: user.to_string(),
+                },
+            )
+            .unwrap()
+    };
+    let next_day = |mock_app: &mut MockApp, days: u64| {
+        mock_app
+            .app
+            .update_block(|block| block.time = block.time.plus_seconds(days * 86400))
+    };
+
+    assert_eq!(
+        query_bonuses(&mock_app),
+        UserBonusesResponse {
+            campaign_id: 1,
+            first_trade_bonus: Uint128::from(5u128),
+            streak_days: 1,
+            streak_multiplier: None,
+        }
+    );
+
+    // trade without fees earns nothing, bonus and streak are left for the next trade
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::TriggerCashBack {
+                user: user.to_string(),
+                tokens: vec![],
+                strict: None,
+            },
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm-cash_back_skipped")
+            .add_attribute("user", "addr000")
+            .add_attribute("reason", "zero_amount")
+    ));
+    next_day(&mut mock_app, 1);
+    assert_eq!(
+        query_bonuses(&mock_app).first_trade_bonus,
+        Uint128::from(5u128)
+    );
+    assert_eq!(query_bonuses(&mock_app).streak_days, 1);
+
+    // first trade gets the one-off bonus
+    let res = trigger(&mut mock_app);
+    assert!(res.has_event(
+        &Event::new("wasm")
+            .add_attribute("cash_back_amount", "15")
+            .add_attribute("first_trade_bonus", "5")
+    ));
+
+    // trading again on the same day does not extend the streak
+    let res = trigger(&mut mock_app);
+    assert!(res.has_event(&Event::new("wasm").add_attribute("cash_back_amount", "10")));
+    next_day(&mut mock_app, 1);
+    trigger(&mut mock_app);
+
+    // third consecutive day doubles the cash back percent
+    next_day(&mut mock_app, 1);
+    assert_eq!(
+        query_bonuses(&mock_app),
+        UserBonusesResponse {
+            campaign_id: 1,
+            first_trade_bonus: Uint128::zero(),
+            streak_days: 3,
+            streak_multiplier: Some(Decimal::percent(200)),
+        }
+    );
+    let res = trigger(&mut mock_app);
+    assert!(res.has_event(
+        &Event::new("wasm")
+            .add_attribute("cash_back_percent", "0.2")
+            .add_attribute("cash_back_amount", "20")
+            .add_attribute("streak_days", "3")
+    ));
+
+    // skipping a day resets the streak
+    next_day(&mut mock_app, 2);
+    assert_eq!(query_bonuses(&mock_app).streak_days, 1);
+    let res = trigger(&mut mock_app);
+    assert!(res.has_event(&Event::new("wasm").add_attribute("cash_back_amount", "10")));
+
+    let pending: Uint128 = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::PendingCashBack {
+                user: user.to_string(),
+            },
+        )
+        .unwrap();
+    assert_eq!(pending, Uint128::from(65u128));
+}
+
+#[test]
+fn test_campaign_windows() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
+        .unwrap();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::WhitelistContract {
+                contract: "contract001".to_string(),
+                label: None,
+                kind: None,
+                multiplier: None,
+                allowed_fee_tokens: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 1000,
+                reward_token: underlying_token.clone(),
+                total_reward: Uint128::from(1000u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let add_window = |start: u64, end: u64| ExecuteMsg::AddCampaignWindow {
+        id: 1,
+        window: MultiplierWindow {
+            start,
+            end,
+            multiplier: Decimal::percent(200),
+        },
+    };
+
+    // add window failed, unauthorized
+    let err = mock_app
+        .execute(
+            Addr::unchecked("addr000"),
+            cash_back_addr.clone(),
+            &add_window(current + 100, current + 200),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
+
+    // add window failed, outside the campaign or invalid range
+    for (start, end) in [
+        (current + 900, current + 1100),
+        (current + 200, current + 100),
+    ] {
+        let err = mock_app
+            .execute(
+                Addr::unchecked(owner),
+                cash_back_addr.clone(),
+                &add_window(start, end),
+                &[],
+            )
+            .unwrap_err();
+        assert!(err.contains(&ContractError::InvalidWindow {}.to_string()));
+    }
+
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &add_window(current + 100, current + 200),
+            &[],
+        )
+        .unwrap();
+
+    // add window failed, overlap
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &add_window(current + 150, current + 300),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::WindowOverlap {
+            start: current + 100,
+            end: current + 200,
+        }
+        .to_string()
+    ));
+
+    let windows: Vec<MultiplierWindow> = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::CampaignWindows { id: 1 })
+        .unwrap();
+    assert_eq!(
+        windows,
+        vec![MultiplierWindow {
+            start: current + 100,
+            end: current + 200,
+            multiplier: Decimal::percent(200),
+        }]
+    );
+
+    // edit campaign failed, window would be outside the campaign
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::EditCampaign {
+                id: 1,
+                start: None,
+                end: Some(current + 150),
+                total_reward: None,
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::InvalidWindow {}.to_string()));
+
+    let trigger_msg = ExecuteMsg::TriggerCashBack {
+        user: "addr000".to_string(),
+        tokens: vec![Asset {
+            info: AssetInfo::NativeToken {
+                denom: "orai".to_string(),
+            },
+            amount: Uint128::from(100u128),
+        }],
+        strict: None,
+    };
+
+    // before the window
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm").add_attribute("cash_back_amount", "10")));
+
+    // inside the window, cash back is doubled
+    mock_app
+        .app
+        .update_block(|block| block.time = block.time.plus_seconds(120));
+    let res: SimulateCashBackResponse = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::SimulateCashBack {
+                user: "addr000".to_string(),
+                tokens: vec![],
+            },
+        )
+        .unwrap();
+    assert_eq!(res.cash_back_percent, Decimal::percent(20));
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm")
+            .add_attribute("cash_back_percent", "0.2")
+            .add_attribute("cash_back_amount", "20")
+    ));
+
+    // remove window
+    let remove_window = ExecuteMsg::RemoveCampaignWindow {
+        id: 1,
+        start: current + 100,
+    };
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &remove_window,
+            &[],
+        )
+        .unwrap();
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &remove_window,
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::WindowNotFound {}.to_string()));
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm").add_attribute("cash_back_amount", "10")));
+}
+
+#[test]
+fn test_campaign_multipliers() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
+    mock_app
+        .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
+        .unwrap();
+    for contract in ["contract001", "contract002"] {
+        mock_app
+            .execute(
+                Addr::unchecked(owner),
+                cash_back_addr.clone(),
+                &ExecuteMsg::WhitelistContract {
+                    contract: contract.to_string(),
+                    label: None,
+                    kind: None,
+                    multiplier: None,
+                    allowed_fee_tokens: None,
+                },
+                &[],
+            )
+            .unwrap();
+    }
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 300,
+                reward_token: underlying_token.clone(),
+                total_reward: Uint128::from(1000u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap();
+
+    let native = |denom: &str| AssetInfo::NativeToken {
+        denom: denom.to_string(),
+    };
+
+    // set multipliers failed, duplicate fee token
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::SetCampaignMultipliers {
+                id: 1,
+                multipliers: CampaignMultipliersInfo {
+                    contracts: vec![],
+                    tokens: vec![
+                        (native("usdt"), Decimal::zero()),
+                        (native("usdt"), Decimal::percent(50)),
+                    ],
+                },
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::DuplicateMultiplier {
+            key: native("usdt").to_string()
+        }
+        .to_string()
+    ));
+
+    let tokens = vec![
+        (native("usdt"), Decimal::zero()),
+        (native("atom"), Decimal::percent(50)),
+    ];
+    let set_multipliers = |contract: &str| ExecuteMsg::SetCampaignMultipliers {
+        id: 1,
+        multipliers: CampaignMultipliersInfo {
+            contracts: vec![(contract.to_string(), Decimal::percent(200))],
+            tokens: tokens.clone(),
+        },
+    };
+
+    // set multipliers failed, contract address is not normalized
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &set_multipliers("Contract001"),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains("Invalid input"));
+
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &set_multipliers("contract001"),
+            &[],
+        )
+        .unwrap();
+    let res: CampaignMultipliers = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::CampaignMultipliers { id: 1 },
+        )
+        .unwrap();
+    assert_eq!(
+        res,
+        CampaignMultipliers {
+            contracts: vec![(Addr::unchecked("contract001"), Decimal::percent(200))],
+            tokens,
+        }
+    );
+
+    // effective multiplier of contract and fee token
+    for (contract, denom, expected) in [
+        ("contract001", "orai", Decimal::percent(200)),
+        ("contract001", "atom", Decimal::one()),
+        ("contract001", "usdt", Decimal::zero()),
+        ("contract002", "atom", Decimal::percent(50)),
+    ] {
+        let res: Decimal = mock_app
+            .query(
+                cash_back_addr.clone(),
+                &QueryMsg::EffectiveMultiplier {
+                    contract: contract.to_string(),
+                    token: native(denom),
+                },
+            )
+            .unwrap();
+        assert_eq!(res, expected);
+    }
+
+    let trigger_msg = ExecuteMsg::TriggerCashBack {
+        user: "addr000".to_string(),
+        tokens: ["orai", "usdt", "atom"]
+            .into_iter()
+            .map(|denom| Asset {
+                info: native(denom),
+                amount: Uint128::from(100u128),
+            })
+            .collect(),
+        strict: None,
+    };
+
+    // 20 from orai, 0 from usdt, 10 from atom
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm").add_attribute("cash_back_amount", "30")));
+
+    // 10 from orai, 0 from usdt, 5 from atom
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract002"),
+            cash_back_addr.clone(),
+            &trigger_msg,
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm").add_attribute("cash_back_amount", "15")));
+}
+
+#[test]
+fn test_migrate() {
+    // storage layouts of 0.1.0
+    #[cosmwasm_schema::cw_serde]
+    struct ConfigV0_1 {
+        owner: Addr,
+        underlying_token: AssetInfo,
+        rules: Vec<(Uint128, Decimal)>,
+    }
+    #[cosmwasm_schema::cw_serde]
+    struct CampaignV0_1 {
+        id: u64,
+        start: u64,
+        end: u64,
+        total_reward: Uint128,
+        reward_token: AssetInfo,
+        distributed_amount: Uint128,
+    }
+
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    // legacy rules were not bounded by a max percent
+    let rules = vec![
+        (Uint128::from(100u128), Decimal::percent(10)),
+        (Uint128::from(200u128), Decimal::percent(60)),
+    ];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), None)
+        .unwrap();
+
+    // version is stored at instantiate
+    let version = cw2::query_contract_info(&mock_app.as_querier(), cash_back_addr.clone()).unwrap();
+    assert_eq!(version.contract, "crates.io:cash-back-contracts");
+    assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+
+    // rewrite storage in the 0.1.0 layout, deployed without cw2 info,
+    // addresses were not validated so they may be stored with another case
+    let legacy_token = AssetInfo::Token {
+        contract_addr: Addr::unchecked(oraix_token.as_str().to_uppercase()),
+    };
+    {
+        let mut storage = mock_app.app.contract_storage_mut(&cash_back_addr);
+        cw2::CONTRACT.remove(storage.as_mut());
+        Item::<ConfigV0_1>::new("config")
+            .save(
+                storage.as_mut(),
+                &ConfigV0_1 {
+                    owner: Addr::unchecked(owner.to_uppercase()),
+                    underlying_token: legacy_token.clone(),
+                    rules: rules.clone(),
+                },
+            )
+            .unwrap();
+        Item::<Vec<Addr>>::new("whitelist_contract")
+            .save(
+                storage.as_mut(),
+                &vec![
+                    Addr::unchecked("contract001"),
+                    Addr::unchecked("CONTRACT002"),
+                ],
+            )
+            .unwrap();
+        Map::<u64, CampaignV0_1>::new("campaign")
+            .save(
+                storage.as_mut(),
+                1,
+                &CampaignV0_1 {
+                    id: 1,
+                    start: 100,
+                    end: 200,
+                    total_reward: Uint128::from(1000u128),
+                    reward_token: legacy_token.clone(),
+                    distributed_amount: Uint128::from(100u128),
+                },
+            )
+            .unwrap();
+        Item::<u64>::new("last_campaign_id")
+            .save(storage.as_mut(), &1)
+            .unwrap();
+        for (user, amount) in [("addr000", 100u128), ("ADDR000", 50u128)] {
+            Map::<&Addr, Uint128>::new("pending_cash_back")
+                .save(
+                    storage.as_mut(),
+                    &Addr::unchecked(user),
+                    &Uint128::from(amount),
+                )
+                .unwrap();
+            Map::<(u64, &Addr), Uint128>::new("user_cash_back")
+                .save(
+                    storage.as_mut(),
+                    (1, &Addr::unchecked(user)),
+                    &Uint128::from(amount),
+                )
+                .unwrap();
+        }
+    }
+
+    // migrate from the legacy layout
+    let res = mock_app
+        .migrate(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &MigrateMsg {},
+        )
+        .unwrap();
+    assert!(res.has_event(
+        &Event::new("wasm")
+            .add_attribute("from_version", "0.1.0")
+            .add_attribute("to_version", env!("CARGO_PKG_VERSION"))
+            .add_attribute("migrations", "1")
+    ));
+
+    let config: Config = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::Config {})
+        .unwrap();
+    assert_eq!(
+        config,
+        Config {
+            owner: Some(Addr::unchecked(owner)),
+            underlying_token: underlying_token.clone(),
+            rules,
+            max_cash_back_percent: Decimal::percent(60),
+            rule_mode: RuleMode::Step,
+            verify_fees: false,
+            treasury: None,
+            referral_percent: Decimal::zero(),
+            timelock: 0,
+        }
+    );
+    // config keeps accepting updates with the legacy rules
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::UpdateConfig {
+                underlying_token: None,
+                rules: None,
+                max_cash_back_percent: None,
+                rule_mode: None,
+                verify_fees: None,
+                treasury: None,
+                referral_percent: Some(Decimal::percent(10)),
+                timelock: None,
+            },
+            &[],
+        )
+        .unwrap();
+    let contracts: Vec<WhitelistContractResponse> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::WhitelistContracts {
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        contracts
+            .iter()
+            .map(|contract| (contract.contract.as_str(), contract.info.enabled))
+            .collect::<Vec<_>>(),
+        vec![("contract001", true), ("contract002", true)]
+    );
+    let campaign: Campaign = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::Campaign { id: 1 })
+        .unwrap();
+    assert_eq!(
+        campaign,
+        Campaign {
+            id: 1,
+            start: 100,
+            end: 200,
+            total_reward: Uint128::from(1000u128),
+            reward_token: underlying_token.clone(),
+            distributed_amount: Uint128::from(100u128),
+            windows: vec![],
+        }
+    );
+
+    // user keys are left to normalize in batches
+    let query_pending = |mock_app: &MockApp| -> Uint128 {
+        mock_app
+            .query(
+                cash_back_addr.clone(),
+                &QueryMsg::PendingCashBack {
+                    user: "addr000".to_string(),
+                },
+            )
+            .unwrap()
+    };
+    let key_migration: Option<KeyMigration> = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::KeyMigration {})
+        .unwrap();
+    assert_eq!(
+        key_migration,
+        Some(KeyMigration::PendingCashBack { start_after: None })
+    );
+    assert_eq!(query_pending(&mock_app), Uint128::from(100u128));
+    let migrate_keys = ExecuteMsg::MigrateKeys { limit: Some(1) };
+    let err = mock_app
+        .execute(
+            Addr::unchecked("addr000"),
+            cash_back_addr.clone(),
+            &migrate_keys,
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
+    // one key per call: two pending cash back keys, two user cash back keys, and an empty
+    // page closing each map
+    let mut calls = 0;
+    loop {
+        let res = mock_app
+            .execute(
+                Addr::unchecked(owner),
+                cash_back_addr.clone(),
+                &migrate_keys,
+                &[],
+            )
+            .unwrap();
+        calls += 1;
+        if res.has_event(&Event::new("wasm").add_attribute("done", "true")) {
+            break;
+        }
+    }
+    assert_eq!(calls, 5);
+    let key_migration: Option<KeyMigration> = mock_app
+        .query(cash_back_addr.clone(), &QueryMsg::KeyMigration {})
+        .unwrap();
+    assert_eq!(key_migration, None);
+    assert_eq!(query_pending(&mock_app), Uint128::from(150u128));
+    {
+        let storage = mock_app.app.contract_storage(&cash_back_addr);
+        assert_eq!(
+            USER_CASH_BACK
+                .load(storage.as_ref(), (1, &Addr::unchecked("addr000")))
+                .unwrap(),
+            Uint128::from(150u128)
+        );
+        assert!(!USER_CASH_BACK.has(storage.as_ref(), (1, &Addr::unchecked("ADDR000"))));
+    }
+    // user index is built from existing cash back
+    let history: Vec<UserCashBackResponse> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::UserHistory {
+                user: "addr000".to_string(),
+                start_after: None,
+                limit: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        history,
+        vec![UserCashBackResponse {
+            campaign_id: 1,
+            reward_token: underlying_token.clone(),
+            earned: Uint128::from(150u128),
+            claimed: Uint128::zero(),
+            pending: Uint128::from(150u128),
+        }]
+    );
+    let version = cw2::query_contract_info(&mock_app.as_querier(), cash_back_addr.clone()).unwrap();
+    assert_eq!(version.version, env!("CARGO_PKG_VERSION"));
+
+    // migrating to the same version runs no migration
+    let res = mock_app
+        .migrate(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &MigrateMsg {},
+        )
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm").add_attribute("migrations", "0")));
+
+    // downgrade is refused
+    cw2::set_contract_version(
+        mock_app.app.contract_storage_mut(&cash_back_addr).as_mut(),
+        "crates.io:cash-back-contracts",
+        "9.0.0",
+    )
+    .unwrap();
+    let err = mock_app
+        .migrate(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &MigrateMsg {},
+        )
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::CannotDowngrade {
+            stored: "9.0.0".to_string(),
+            new: env!("CARGO_PKG_VERSION").to_string(),
+        }
+        .to_string()
+    ));
+
+    // another contract can not be migrated
+    cw2::set_contract_version(
+        mock_app.app.contract_storage_mut(&cash_back_addr).as_mut(),
+        "crates.io:cw20-base",
+        "0.1.0",
+    )
+    .unwrap();
+    let err = mock_app
+        .migrate(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &MigrateMsg {},
+        )
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::InvalidContractName {
+            contract: "crates.io:cw20-base".to_string(),
+        }
+        .to_string()
+    ));
+}
+
+#[test]
+fn test_ownership() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token,
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), None)
+        .unwrap();
+    let new_owner = Addr::unchecked("new_owner");
+    let current = mock_app.app.block_info().time.seconds();
+    let query_ownership = |mock_app: &MockApp| -> OwnershipResponse {
+        mock_app
+            .query(cash_back_addr.clone(), &QueryMsg::Ownership {})
+            .unwrap()
+    };
+    let propose = |expires: Option<u64>| ExecuteMsg::ProposeOwner {
+        owner: new_owner.to_string(),
+        expires,
+    };
+
+    // propose failed, unauthorized
+    let err = mock_app
+        .execute(
+            new_owner.clone(),
+            cash_back_addr.clone(),
+            &propose(None),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
+
+    // propose failed, already expired
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &propose(Some(current)),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::OwnershipProposalExpired {}.to_string()));
+
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &propose(Some(current + 100)),
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        query_ownership(&mock_app),
+        OwnershipResponse {
+            owner: Some(Addr::unchecked(owner)),
+            pending_owner: Some(new_owner.clone()),
+            pending_expires: Some(current + 100),
+        }
+    );
+
+    // only the proposed owner can accept
+    let err = mock_app
+        .execute(
+            Addr::unchecked("addr000"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::AcceptOwnership {},
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
+
+    // cancel proposal
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CancelOwnershipProposal {},
+            &[],
+        )
+        .unwrap();
+    assert_eq!(query_ownership(&mock_app).pending_owner, None);
+    let err = mock_app
+        .execute(
+            new_owner.clone(),
+            cash_back_addr.clone(),
+            &ExecuteMsg::AcceptOwnership {},
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::NoOwnershipProposal {}.to_string()));
+
+    // accept failed, proposal expired
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &propose(Some(current + 100)),
+            &[],
+        )
+        .unwrap();
+    mock_app
+        .app
+        .update_block(|block| block.time = block.time.plus_seconds(200));
+    let err = mock_app
+        .execute(
+            new_owner.clone(),
+            cash_back_addr.clone(),
+            &ExecuteMsg::AcceptOwnership {},
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::OwnershipProposalExpired {}.to_string()));
+
+    // accept successful
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &propose(None),
+            &[],
+        )
+        .unwrap();
+    mock_app
+        .execute(
+            new_owner.clone(),
+            cash_back_addr.clone(),
+            &ExecuteMsg::AcceptOwnership {},
+            &[],
+        )
+        .unwrap();
+    assert_eq!(
+        query_ownership(&mock_app),
+        OwnershipResponse {
+            owner: Some(new_owner.clone()),
+            pending_owner: None,
+            pending_expires: None,
+        }
+    );
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &propose(None),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
+
+    // renounce ownership, owner messages fail afterwards
+    mock_app
+        .execute(
+            new_owner.clone(),
+            cash_back_addr.clone(),
+            &ExecuteMsg::RenounceOwnership {},
+            &[],
+        )
+        .unwrap();
+    assert_eq!(query_ownership(&mock_app).owner, None);
+    let err = mock_app
+        .execute(
+            new_owner.clone(),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 300,
+                reward_token: underlying_token,
+                total_reward: Uint128::from(1000u128),
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
+}
+
+#[test]
+fn test_roles() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token,
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), None)
+        .unwrap();
+    let grant = |user: &str, role: Role| ExecuteMsg::GrantRole {
+        user: user.to_string(),
+        role,
+    };
+    let whitelist = |contract: &str| ExecuteMsg::WhitelistContract {
+        contract: contract.to_string(),
+        label: None,
+        kind: None,
+        multiplier: None,
+        allowed_fee_tokens: None,
+    };
+    let update_config =
+        |verify_fees: Option<bool>, rule_mode: Option<RuleMode>| ExecuteMsg::UpdateConfig {
+            underlying_token: None,
+            rules: None,
+            max_cash_back_percent: None,
+            rule_mode,
+            verify_fees,
+            treasury: verify_fees.map(|_| "treasury".to_string()),
+            referral_percent: None,
+            timelock: None,
+        };
+    let current = mock_app.app.block_info().time.seconds();
+    let create_campaign = ExecuteMsg::CreateCampaign {
+        start: current,
+        end: current + 300,
+        reward_token: underlying_token.clone(),
+        total_reward: Uint128::from(1000u128),
+        require_solvency: None,
     };
 
-    let msg = ExecuteMsg::TriggerCashBack {
-        user: Addr::unchecked("addr000"),
-        tokens: vec![
-            Asset {
-                info: orai.clone(),
-                amount: Uint128::from(1000u128),
+    // grant failed, unauthorized
+    let err = mock_app
+        .execute(
+            Addr::unchecked("manager"),
+            cash_back_addr.clone(),
+            &grant("manager", Role::CampaignManager),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
+
+    for (user, role) in [
+        ("manager", Role::CampaignManager),
+        ("keeper", Role::Keeper),
+        ("admin2", Role::Admin),
+        ("treasurer", Role::Treasurer),
+    ] {
+        mock_app
+            .execute(
+                Addr::unchecked(owner),
+                cash_back_addr.clone(),
+                &grant(user, role),
+                &[],
+            )
+            .unwrap();
+    }
+    let members: Vec<RoleInfo> = mock_app
+        .query(
+            cash_back_addr.clone(),
+            &QueryMsg::RoleMembers {
+                start_after: None,
+                limit: Some(2),
             },
-            Asset {
-                info: usdt_info.clone(),
-                amount: Uint128::from(2000u128),
+        )
+        .unwrap();
+    assert_eq!(
+        members,
+        vec![
+            RoleInfo {
+                user: Addr::unchecked("admin2"),
+                roles: vec![Role::Admin],
             },
-        ],
-    };
+            RoleInfo {
+                user: Addr::unchecked("keeper"),
+                roles: vec![Role::Keeper],
+            },
+        ]
+    );
 
-    // case 1:  contract not register yet
+    // campaign manager can create campaigns but not whitelist contracts
     mock_app
         .execute(
-            Addr::unchecked("contract002"),
+            Addr::unchecked("manager"),
             cash_back_addr.clone(),
-            &msg,
+            &create_campaign,
             &[],
         )
         .unwrap();
-    let pending_cash_back: Uint128 = mock_app
-        .query(
+    let err = mock_app
+        .execute(
+            Addr::unchecked("manager"),
             cash_back_addr.clone(),
-            &QueryMsg::PendingCashBack {
-                user: Addr::unchecked("addr000"),
-            },
+            &whitelist("contract001"),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
+
+    // treasurer can update fee settings only
+    mock_app
+        .execute(
+            Addr::unchecked("treasurer"),
+            cash_back_addr.clone(),
+            &update_config(Some(true), None),
+            &[],
         )
         .unwrap();
-    assert_eq!(pending_cash_back, Uint128::zero());
+    let err = mock_app
+        .execute(
+            Addr::unchecked("treasurer"),
+            cash_back_addr.clone(),
+            &update_config(Some(false), Some(RuleMode::Linear)),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
 
-    // case 2: don;t exist campaign
+    // admin performs every role and manages roles
     mock_app
         .execute(
-            Addr::unchecked("contract001"),
+            Addr::unchecked("admin2"),
             cash_back_addr.clone(),
-            &msg,
+            &whitelist("contract001"),
             &[],
         )
         .unwrap();
-    let pending_cash_back: Uint128 = mock_app
-        .query(
+    mock_app
+        .execute(
+            Addr::unchecked("admin2"),
             cash_back_addr.clone(),
-            &QueryMsg::PendingCashBack {
-                user: Addr::unchecked("addr000"),
-            },
+            &grant("manager", Role::WhitelistManager),
+            &[],
         )
         .unwrap();
-    assert_eq!(pending_cash_back, Uint128::zero());
-
-    // case 3: trigger success
-    let current = mock_app.app.block_info().time.seconds();
-    let msg = ExecuteMsg::CreateCampaign {
-        start: current,
-        end: current + 300,
-        reward_token: underlying_token.clone(),
-        total_reward: Uint128::from(1000u128),
-    };
     mock_app
-        .execute(Addr::unchecked(owner), cash_back_addr.clone(), &msg, &[])
+        .execute(
+            Addr::unchecked("manager"),
+            cash_back_addr.clone(),
+            &whitelist("contract002"),
+            &[],
+        )
         .unwrap();
 
-    let msg = ExecuteMsg::TriggerCashBack {
-        user: Addr::unchecked("addr000"),
-        tokens: vec![
-            Asset {
-                info: orai.clone(),
-                amount: Uint128::from(1000u128),
-            },
-            Asset {
-                info: usdt_info.clone(),
-                amount: Uint128::from(2000u128),
-            },
-        ],
-    };
-
+    // only keepers distribute pending cash back
     mock_app
         .execute(
-            Addr::unchecked("contract001"),
+            Addr::unchecked("keeper"),
             cash_back_addr.clone(),
-            &msg,
+            &ExecuteMsg::CashBack {},
             &[],
         )
         .unwrap();
+    let err = mock_app
+        .execute(
+            Addr::unchecked("manager"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CashBack {},
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
 
-    // pending still 0, because balance  = 0 => no refund
-    let pending_cash_back: Uint128 = mock_app
+    // revoke role
+    mock_app
+        .execute(
+            Addr::unchecked("admin2"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::RevokeRole {
+                user: "manager".to_string(),
+                role: Role::CampaignManager,
+            },
+            &[],
+        )
+        .unwrap();
+    let roles: Vec<Role> = mock_app
         .query(
             cash_back_addr.clone(),
-            &QueryMsg::PendingCashBack {
-                user: Addr::unchecked("addr000"),
+            &QueryMsg::Roles {
+                user: "manager".to_string(),
             },
         )
         .unwrap();
-    assert_eq!(pending_cash_back, Uint128::zero());
+    assert_eq!(roles, vec![Role::WhitelistManager]);
+    let err = mock_app
+        .execute(
+            Addr::unchecked("manager"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::EditCampaign {
+                id: 1,
+                start: None,
+                end: None,
+                total_reward: None,
+                require_solvency: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
 
-    // update balance
+    // ownership stays with the owner
+    let err = mock_app
+        .execute(
+            Addr::unchecked("admin2"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::ProposeOwner {
+                owner: "admin2".to_string(),
+                expires: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
+
+    // admin grants are dropped when the owner renounces, other roles are kept
     mock_app
-        .mint_token(owner, "addr000", oraix_token.as_str(), 150u128)
+        .execute(
+            Addr::unchecked("admin2"),
+            cash_back_addr.clone(),
+            &grant("keeper", Role::Admin),
+            &[],
+        )
         .unwrap();
-
-    // try trigger cash back again, fee is tier 1 (10%) => cashBackAmount = 100 + 200 = 300
     mock_app
         .execute(
-            Addr::unchecked("contract001"),
+            Addr::unchecked(owner),
             cash_back_addr.clone(),
-            &msg,
+            &ExecuteMsg::RenounceOwnership {},
             &[],
         )
         .unwrap();
-    let pending_cash_back: Uint128 = mock_app
+    let members: Vec<RoleInfo> = mock_app
         .query(
             cash_back_addr.clone(),
-            &QueryMsg::PendingCashBack {
-                user: Addr::unchecked("addr000"),
+            &QueryMsg::RoleMembers {
+                start_after: None,
+                limit: None,
             },
         )
         .unwrap();
-    assert_eq!(pending_cash_back, Uint128::from(300u128));
+    assert_eq!(
+        members,
+        vec![
+            RoleInfo {
+                user: Addr::unchecked("keeper"),
+                roles: vec![Role::Keeper],
+            },
+            RoleInfo {
+                user: Addr::unchecked("manager"),
+                roles: vec![Role::WhitelistManager],
+            },
+            RoleInfo {
+                user: Addr::unchecked("treasurer"),
+                roles: vec![Role::Treasurer],
+            },
+        ]
+    );
+    let err = mock_app
+        .execute(
+            Addr::unchecked("admin2"),
+            cash_back_addr.clone(),
+            &whitelist("contract003"),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
+    mock_app
+        .execute(
+            Addr::unchecked("keeper"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CashBack {},
+            &[],
+        )
+        .unwrap();
+}
 
-    // update balance to tier 2: 20%
+#[test]
+fn test_pause() {
+    let mut mock_app = MockApp::new(&[("admin", &[])]);
+    let owner = "admin";
+    let token = "oraix";
+    let rules = vec![(Uint128::from(100u128), Decimal::percent(10))];
+
+    let oraix_token = mock_app.create_token(owner, token, 0u128);
+    let underlying_token = AssetInfo::Token {
+        contract_addr: oraix_token.clone(),
+    };
+    let cash_back_addr = mock_app
+        .create_cash_back_contract(owner, underlying_token.clone(), Some(rules))
+        .unwrap();
     mock_app
         .mint_token(owner, "addr000", oraix_token.as_str(), 100u128)
         .unwrap();
     mock_app
         .execute(
-            Addr::unchecked("contract001"),
+            Addr::unchecked(owner),
             cash_back_addr.clone(),
-            &msg,
+            &ExecuteMsg::WhitelistContract {
+                contract: "contract001".to_string(),
+                label: None,
+                kind: None,
+                multiplier: None,
+                allowed_fee_tokens: None,
+            },
             &[],
         )
         .unwrap();
-    let pending_cash_back: Uint128 = mock_app
-        .query(
+    let current = mock_app.app.block_info().time.seconds();
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
             cash_back_addr.clone(),
-            &QueryMsg::PendingCashBack {
-                user: Addr::unchecked("addr000"),
+            &ExecuteMsg::CreateCampaign {
+                start: current,
+                end: current + 300,
+                reward_token: underlying_token.clone(),
+                total_reward: Uint128::from(1000u128),
+                require_solvency: None,
             },
+            &[],
         )
         .unwrap();
-    assert_eq!(pending_cash_back, Uint128::from(900u128));
 
-    // try calc cash back
-    let cash_back_msg = ExecuteMsg::CashBack {};
+    let trigger_msg = |strict: bool| ExecuteMsg::TriggerCashBack {
+        user: "addr000".to_string(),
+        tokens: vec![Asset {
+            info: AssetInfo::NativeToken {
+                denom: "orai".to_string(),
+            },
+            amount: Uint128::from(1000u128),
+        }],
+        strict: Some(strict),
+    };
+    let query_status = |mock_app: &MockApp| -> PauseStatus {
+        mock_app
+            .query(cash_back_addr.clone(), &QueryMsg::PauseStatus {})
+            .unwrap()
+    };
+
+    // pause failed, unauthorized
+    let err = mock_app
+        .execute(
+            Addr::unchecked("guardian"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::Pause { function: None },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(&ContractError::Unauthorized {}.to_string()));
     mock_app
         .execute(
             Addr::unchecked(owner),
             cash_back_addr.clone(),
-            &cash_back_msg,
+            &ExecuteMsg::GrantRole {
+                user: "guardian".to_string(),
+                role: Role::Guardian,
+            },
             &[],
         )
         .unwrap();
 
-    // after call this func, pending become zero
-    let pending_cash_back: Uint128 = mock_app
-        .query(
+    // pause triggers only
+    mock_app
+        .execute(
+            Addr::unchecked("guardian"),
             cash_back_addr.clone(),
-            &QueryMsg::PendingCashBack {
-                user: Addr::unchecked("addr000"),
+            &ExecuteMsg::Pause {
+                function: Some(PausableFunction::Triggers),
             },
+            &[],
         )
         .unwrap();
-    assert_eq!(pending_cash_back, Uint128::zero());
-
-    // continue trigger cashback adn  reach limit of campaign (remaining 100 token)
-    mock_app
+    assert_eq!(
+        query_status(&mock_app),
+        PauseStatus {
+            triggers: true,
+            payouts: false,
+            claims: false,
+        }
+    );
+    let res = mock_app
         .execute(
             Addr::unchecked("contract001"),
             cash_back_addr.clone(),
-            &msg,
+            &trigger_msg(false),
             &[],
         )
         .unwrap();
-    let pending_cash_back: Uint128 = mock_app
-        .query(
+    assert!(res.has_event(&Event::new("wasm-cash_back_skipped").add_attribute("reason", "paused")));
+    let err = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
             cash_back_addr.clone(),
-            &QueryMsg::PendingCashBack {
-                user: Addr::unchecked("addr000"),
-            },
+            &trigger_msg(true),
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::Paused {
+            function: PausableFunction::Triggers
+        }
+        .to_string()
+    ));
+    mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CashBack {},
+            &[],
         )
         .unwrap();
-    assert_eq!(pending_cash_back, Uint128::from(100u128));
 
-    // query last campaign
-    let last_round: Campaign = mock_app
-        .query(cash_back_addr.clone(), &QueryMsg::LastCampaign {})
+    // pause everything
+    mock_app
+        .execute(
+            Addr::unchecked("guardian"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::Pause { function: None },
+            &[],
+        )
         .unwrap();
-    assert_eq!(
-        last_round,
-        Campaign {
-            id: 1,
-            start: current,
-            end: current + 300,
-            reward_token: underlying_token.clone(),
-            total_reward: Uint128::from(1000u128),
-            distributed_amount: Uint128::from(1000u128),
+    let err = mock_app
+        .execute(
+            Addr::unchecked(owner),
+            cash_back_addr.clone(),
+            &ExecuteMsg::CashBack {},
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::Paused {
+            function: PausableFunction::Payouts
         }
-    );
+        .to_string()
+    ));
+    let err = mock_app
+        .execute(
+            Addr::unchecked("addr000"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::ClaimReferralReward {},
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.contains(
+        &ContractError::Paused {
+            function: PausableFunction::Claims
+        }
+        .to_string()
+    ));
+
+    // unpause everything, triggers accrue again
+    mock_app
+        .execute(
+            Addr::unchecked("guardian"),
+            cash_back_addr.clone(),
+            &ExecuteMsg::Unpause { function: None },
+            &[],
+        )
+        .unwrap();
+    assert_eq!(query_status(&mock_app), PauseStatus::default());
+    let res = mock_app
+        .execute(
+            Addr::unchecked("contract001"),
+            cash_back_addr.clone(),
+            &trigger_msg(true),
+            &[],
+        )
+        .unwrap();
+    assert!(res.has_event(&Event::new("wasm").add_attribute("cash_back_amount", "100")));
 }